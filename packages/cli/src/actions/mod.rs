@@ -1,7 +1,15 @@
 mod template_action;
 mod template_fetch;
+mod template_init;
+mod template_publish;
+mod template_undo;
+mod template_update;
 mod template_use;
 
 pub use template_action::*;
 pub use template_fetch::*;
+pub use template_init::*;
+pub use template_publish::*;
+pub use template_undo::*;
+pub use template_update::*;
 pub use template_use::*;
@@ -6,11 +6,13 @@ use crate::{
         CONFIG_FILE, TEMPLATE_DOCS_URL, TEMPLATE_ROOT_FOLDER, TEMPLATE_SELECT, TEMPLATE_VARIABLE,
     },
     search_folder::SearchFolder,
-    template::{TemplateConfig, TemplateFolder},
+    template::{TemplateCaseType, TemplateConfig, TemplateFolder},
+    template_case::TemplateCaseResolver,
     template_file_content::TEMPLATE_FILE_CONTENT,
-    template_variable::TemplateVariable,
+    template_variable::{TemplateVariable, TemplateVariableInfo},
 };
 use colored::Colorize;
+use indexmap::IndexMap;
 use std::{
     collections::{HashMap, HashSet},
     fs,
@@ -90,13 +92,13 @@ impl TemplateAction {
                         None
                     };
 
-                if already_set_value.is_some() {
+                if let Some(already_set_value) = already_set_value {
                     let mut want_to_change = false;
                     if !ignore_existing {
                         want_to_change = CliCommands::confirm(&format!(
                             "📔 Do you want to update {} options? Current values: {}",
-                            &variable.raw_value.cyan().bold().italic(),
-                            already_set_value.unwrap().join(", ").bold().magenta()
+                            variable.raw_value.cyan().bold().italic(),
+                            already_set_value.join(", ").bold().magenta()
                         ));
                     }
 
@@ -105,13 +107,11 @@ impl TemplateAction {
                             "{} {:width$} with option values: {}",
                             "Skipping".magenta(),
                             variable.raw_value.bold().yellow(),
-                            already_set_value.unwrap().join(", ").bold().cyan()
+                            already_set_value.join(", ").bold().cyan()
                         );
 
-                        select_options.insert(
-                            variable.raw_value.to_owned(),
-                            already_set_value.unwrap().to_owned(),
-                        );
+                        select_options
+                            .insert(variable.raw_value.to_owned(), already_set_value.to_owned());
                         continue;
                     }
                 }
@@ -120,7 +120,7 @@ impl TemplateAction {
                         "📔 {}/{} Enter options values for {}",
                         index,
                         select_variables.len(),
-                        &variable.raw_value.cyan().bold().italic()
+                        variable.raw_value.cyan().bold().italic()
                     ),
                     "Select values cannot be empty",
                     None,
@@ -351,17 +351,19 @@ impl TemplateAction {
         let file_case_type =
             CliCommands::case_type(Some(CaseType::KebabCase), "Case type for file names");
         let mut config_file = ConfigFile::new();
-        if file_case_type.is_ok() {
-            config_file.case_type.file = file_case_type.unwrap();
+        let mut case_type = TemplateCaseType::new();
+        if let Ok(file_case_type) = file_case_type {
+            case_type.file = file_case_type;
         }
 
         println!();
 
         let content_case_type =
             CliCommands::case_type(Some(CaseType::PascalCase), "Case type for template content");
-        if content_case_type.is_ok() {
-            config_file.case_type.content = content_case_type.unwrap();
+        if let Ok(content_case_type) = content_case_type {
+            case_type.content = content_case_type;
         }
+        config_file.case_type = Some(case_type);
 
         config_file
     }
@@ -441,14 +443,92 @@ impl TemplateAction {
             }
             for (variable, variable_info) in variables {
                 println!(
-                    "{} {} {} {}",
+                    "{} {} {} {}{}",
                     "📔".magenta(),
                     variable.bold().yellow(),
                     "type:".bold().magenta(),
-                    variable_info.template_variable.to_string().bold().cyan()
+                    variable_info.template_variable.to_string().bold().cyan(),
+                    if variable_info.is_auto {
+                        " (auto)".italic()
+                    } else {
+                        "".normal()
+                    }
                 );
             }
             println!();
         }
     }
+
+    /**
+     * Show case type of each variable occurrence and which config level decided it.
+     */
+    pub fn explain_template(
+        local_config: &Config,
+        global_config: &Config,
+        template_folder: &TemplateFolder,
+    ) {
+        let result = SearchFolder::search(&template_folder.path);
+        let case_resolver = TemplateCaseResolver::new(
+            &result.template_config,
+            Some(&local_config.config),
+            &global_config.config,
+        );
+
+        // same variables as rendered, `__var__title__` can be variable `title`
+        let has_key = |key: &str| result.variables.contains_key(key);
+        let mut occurrences = IndexMap::new();
+        for file in result.files.iter() {
+            if file.is_config {
+                continue;
+            }
+            for part in file.template_path.iter() {
+                let variables = TemplateVariableInfo::parse_iter(part.to_str().unwrap());
+                for variable in variables.filter(|item| item.template_variable.is_value()) {
+                    let variable = SearchFolder::resolve_name(variable, has_key);
+                    occurrences.insert((variable.raw_value.to_owned(), true), variable);
+                }
+            }
+            let content = fs::read_to_string(&file.path).unwrap_or_default();
+            let variables = TemplateVariableInfo::parse_iter(&content);
+            for variable in variables.filter(|item| item.template_variable.is_value()) {
+                let variable = SearchFolder::resolve_name(variable, has_key);
+                occurrences.insert((variable.raw_value.to_owned(), false), variable);
+            }
+        }
+
+        println!();
+        println!(
+            "{} {}",
+            "🔎 Case resolution for template:".bold().green(),
+            template_folder.name.bold().green()
+        );
+        println!();
+        if occurrences.is_empty() {
+            println!(
+                "{} {}",
+                "🚨 No variables found.".red(),
+                "Check template file content or paths.".italic()
+            );
+            println!();
+            return;
+        }
+
+        let width = occurrences
+            .keys()
+            .map(|(raw_value, _)| raw_value.len())
+            .max()
+            .unwrap_or_default();
+        for ((raw_value, is_file_path), variable) in occurrences.iter() {
+            let (case_type, source) = case_resolver.resolve(variable, *is_file_path);
+            println!(
+                "{} {:width$} {:7} {:12} {}",
+                "📔".magenta(),
+                raw_value.bold().yellow(),
+                if *is_file_path { "path" } else { "content" },
+                case_type.to_str_name().bold().cyan(),
+                format!("({})", source).italic()
+            );
+        }
+        println!();
+    }
 }
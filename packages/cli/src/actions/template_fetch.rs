@@ -1,104 +1,214 @@
 use crate::{
-    cli_commands::CliCommands, config::Config, constants::TEMPLATE_ROOT_FOLDER,
+    cli_commands::CliCommands,
+    config::Config,
     template::TemplateFolder,
+    template_git::TemplateGit,
+    template_lock::{TemplateLock, TemplateLockEntry},
+    template_source::{FetchedSource, TemplateSource},
 };
 use colored::Colorize;
-use fs_extra::dir::{move_dir, CopyOptions};
-use std::{collections::HashMap, env::temp_dir, fs, path::Path};
+use fs_extra::dir::{copy, CopyOptions};
+use loading::Loading;
+use std::{fs, io::IsTerminal, path::Path};
+
+pub struct TemplateFetchOptions {
+    pub git_ref: Option<String>,
+    pub subdir: Option<String>,
+    /// import single template under this name, see `--as`
+    pub name: Option<String>,
+}
+
 pub struct TemplateFetch;
 
-impl TemplateFetch {
-    pub fn fetch_github(config: &Config, github_url: &str) {
-        // partially search for github url and scan data
-        let github_url = github_url.trim();
+/**
+ * Fetched source with arguments it was fetched with, recorded to gen.lock.
+ */
+struct TemplateFetchSource<'a> {
+    source: &'a str,
+    git_ref: Option<&'a str>,
+    fetched_source: &'a FetchedSource,
+    namespace: String,
+}
 
-        let github_tmp_path = temp_dir().join("github_tmp");
-        println!("github_tmp_path: {:?}", github_tmp_path);
+/**
+ * What to do with fetched template when template with same name already exists.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateConflict {
+    Rename(String),
+    Overwrite,
+    Skip,
+}
 
-        if github_tmp_path.exists() {
-            fs::remove_dir_all(&github_tmp_path).unwrap();
+impl std::fmt::Display for TemplateConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateConflict::Rename(name) => write!(f, "Import as {}", name),
+            TemplateConflict::Overwrite => write!(f, "Overwrite existing template"),
+            TemplateConflict::Skip => write!(f, "Skip"),
         }
+    }
+}
 
-        fs::create_dir_all(&github_tmp_path).unwrap();
-
-        let is_ok = CliCommands::run_terminal_command(&format!(
-            "git clone {} {}",
-            github_url,
-            github_tmp_path.to_str().unwrap()
-        ));
-        if !is_ok {
-            println!("{}", "🚨 Error while cloning repository".red());
-            return;
+impl TemplateFetch {
+    /**
+     * Import templates from git repository, local directory or archive, see `TemplateSource`.
+     * Templates can be taken from `subdir` of the source, given by `--path` or `source#subdir`.
+     * Template colliding with existing one can be imported under another name or namespace.
+     */
+    pub fn fetch(config: &Config, source: &str, options: &TemplateFetchOptions) {
+        let (source, source_subdir) = TemplateSource::split_subdir(source);
+        if options.subdir.is_some() && source_subdir.is_some() {
+            println!(
+                "{}",
+                "🚨 Use either --path or source#subdir, not both.".red()
+            );
+            std::process::exit(1);
+        }
+        if let Some(Err(error)) = options.name.as_deref().map(TemplateFolder::validate_name) {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        let subdir = options.subdir.as_deref().or(source_subdir);
+        if let Some(Err(error)) = subdir.map(TemplateSource::validate_subdir) {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
         }
+        let git_ref = options.git_ref.as_deref();
+        let fetch_path = TemplateGit::temp_directory("fetch").unwrap_or_else(|error| {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        });
 
-        let repo_path = Path::new(&github_tmp_path);
-        let template_folder_path = repo_path.join(TEMPLATE_ROOT_FOLDER);
+        let loading = Loading::default();
+        loading.text(format!("Fetching {}...", source).blue());
+        let template_source = TemplateSource::parse(source);
+        let result = template_source.fetch(git_ref, &fetch_path);
+        loading.end();
 
-        if !template_folder_path.exists() {
-            println!("{}", "🚨 Repository does not contain template folder".red());
-            return;
+        let lock_source = template_source.lock_source();
+        let result = result.and_then(|fetched_source| {
+            let templates = fetched_source.templates(subdir)?;
+            let source = TemplateFetchSource {
+                source: &lock_source,
+                git_ref,
+                fetched_source: &fetched_source,
+                namespace: template_source.namespace(),
+            };
+            TemplateFetch::copy_templates(config, &source, &templates, options.name.as_deref())
+        });
+        // temp dir is removed also when fetch fails
+        let _ = fs::remove_dir_all(&fetch_path);
+        if let Err(error) = result {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
         }
+    }
 
-        let git_config = Config::load_template_folders(&template_folder_path);
+    /**
+     * Let user select fetched templates and copy them to config folder.
+     * Source with single template is copied without asking.
+     */
+    fn copy_templates(
+        config: &Config,
+        source: &TemplateFetchSource,
+        templates: &[TemplateFolder],
+        name: Option<&str>,
+    ) -> Result<(), String> {
+        let mut lock = TemplateLock::load(&config.path)?;
 
-        println!();
-        let git_template_folders = CliCommands::multi_select(
-            "Select templates to be copied",
-            &git_config.template_folders,
-        );
+        let git_template_folders = if templates.len() == 1 {
+            Ok(templates.to_vec())
+        } else {
+            println!();
+            CliCommands::multi_select("Select templates to be copied", templates)
+        };
 
         if git_template_folders.is_err() {
             println!("{}", "🚨 No templates selected".red());
-            return;
+            return Ok(());
         }
 
         let git_template_folders = git_template_folders.unwrap();
 
         if git_template_folders.is_empty() {
             println!("{}", "🚨 No templates selected".red());
-            return;
+            return Ok(());
+        }
+        if name.is_some() && git_template_folders.len() > 1 {
+            return Err("--as can be used only when single template is fetched".to_string());
         }
 
-        let config_template_folders_hash_map: HashMap<String, TemplateFolder> = config
+        let mut template_names = config
             .template_folders
             .iter()
-            .map(|item| (item.name.clone(), item.clone()))
-            .collect();
+            .map(|item| item.name.to_owned())
+            .collect::<Vec<_>>();
 
         for git_template_folder in git_template_folders.iter() {
-            if let Some(item) = config_template_folders_hash_map.get(&git_template_folder.name) {
-                println!();
-                println!(
-                    "{} {}",
-                    "🚨 Template folder already exists.".red(),
-                    item.name
-                );
-                println!();
-                let can_delete = CliCommands::confirm("Do you want to delete it before fetch new?");
-                if can_delete {
-                    fs::remove_dir_all(&item.path).unwrap();
-                } else {
-                    continue;
+            let mut template_name = name
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| git_template_folder.name.to_owned());
+            // name can come from fetched `_.json`, it must stay inside of templates folder
+            TemplateFolder::validate_name(&template_name)?;
+
+            if template_names.contains(&template_name) {
+                match TemplateFetch::resolve_conflict(
+                    &config.path,
+                    &template_name,
+                    source,
+                    &template_names,
+                )? {
+                    TemplateConflict::Rename(name) => template_name = name,
+                    TemplateConflict::Overwrite => {}
+                    TemplateConflict::Skip => continue,
                 }
             }
 
-            let destination = &config.path;
-
-            if !destination.exists() {
-                fs::create_dir_all(destination).unwrap();
+            if !Config::can_add_template(&config.path, &template_name) {
+                return Err(format!(
+                    "Cannot import {}, {} is a template, not a namespace",
+                    template_name,
+                    template_name
+                        .split_once('/')
+                        .map(|(namespace, _)| namespace)
+                        .unwrap_or(&template_name)
+                ));
             }
-
-            let move_result =
-                move_dir(&git_template_folder.path, &config.path, &CopyOptions::new());
-            if move_result.is_err() {
-                println!("{}", "🚨 Error while moving template folder".red());
-                return;
+            let destination = config.path.join(&template_name);
+            let is_same_folder = destination.exists()
+                && fs::canonicalize(&git_template_folder.path).ok()
+                    == fs::canonicalize(&destination).ok();
+            if is_same_folder {
+                return Err(
+                    "Cannot fetch templates from the folder they are fetched to".to_string()
+                );
             }
 
+            // local directory is a source too, so templates are copied, not moved
+            TemplateFetch::copy_template(&git_template_folder.path, &destination)?;
+
+            let hash = TemplateLock::hash_folder(&destination)?;
+            lock.templates.insert(
+                template_name.to_owned(),
+                TemplateLockEntry {
+                    source: source.source.to_string(),
+                    git_ref: source.git_ref.map(|git_ref| git_ref.to_string()),
+                    commit: source.fetched_source.commit.to_owned(),
+                    subdir: TemplateLock::relative_path(
+                        &source.fetched_source.root,
+                        &git_template_folder.path,
+                    ),
+                    hash,
+                },
+            );
+            lock.save(&config.path)?;
+
             println!(
                 "✅ Template folder copied successfully: {}",
-                git_template_folder.name.green().bold()
+                template_name.green().bold()
             );
+            template_names.push(template_name);
         }
         println!();
         println!(
@@ -107,5 +217,73 @@ impl TemplateFetch {
             config.path.to_str().unwrap()
         );
         println!();
+        Ok(())
+    }
+
+    /**
+     * Existing template is kept when user does not want to overwrite it, fetched one is imported
+     * to source namespace instead, e.g. `acme/component`. Without terminal it is done automatically.
+     */
+    fn resolve_conflict(
+        directory: &Path,
+        template_name: &str,
+        source: &TemplateFetchSource,
+        template_names: &[String],
+    ) -> Result<TemplateConflict, String> {
+        let short_name = template_name
+            .rsplit_once('/')
+            .map(|(_, name)| name)
+            .unwrap_or(template_name);
+        let namespaced_name = format!("{}/{}", source.namespace, short_name);
+        let can_rename = TemplateFolder::validate_name(&namespaced_name).is_ok()
+            && !template_names.contains(&namespaced_name)
+            && Config::can_add_template(directory, &namespaced_name);
+
+        if !std::io::stdin().is_terminal() {
+            if !can_rename {
+                return Err(format!(
+                    "Template {} already exists, use --as to import it under another name",
+                    template_name
+                ));
+            }
+            println!(
+                "{} {} {}",
+                "🚨 Template folder already exists:".yellow(),
+                template_name,
+                format!("importing as {}", namespaced_name).yellow()
+            );
+            return Ok(TemplateConflict::Rename(namespaced_name));
+        }
+
+        println!();
+        println!(
+            "{} {}",
+            "🚨 Template folder already exists.".red(),
+            template_name
+        );
+        println!();
+        let mut conflicts = Vec::new();
+        if can_rename {
+            conflicts.push(TemplateConflict::Rename(namespaced_name));
+        }
+        conflicts.push(TemplateConflict::Overwrite);
+        conflicts.push(TemplateConflict::Skip);
+        Ok(CliCommands::select("What do you want to do", &conflicts)
+            .unwrap_or(TemplateConflict::Skip))
+    }
+
+    /**
+     * Replace `destination` folder with content of `template_path`.
+     */
+    pub fn copy_template(template_path: &Path, destination: &Path) -> Result<(), String> {
+        if destination.exists() {
+            fs::remove_dir_all(destination).map_err(|error| error.to_string())?;
+        }
+        fs::create_dir_all(destination).map_err(|error| error.to_string())?;
+        let mut options = CopyOptions::new();
+        options.content_only = true;
+        copy(template_path, destination, &options)
+            .map(|_| ())
+            .map_err(|error| format!("Error while copying template folder: {}", error))
     }
 }
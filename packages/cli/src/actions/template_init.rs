@@ -0,0 +1,77 @@
+use crate::{
+    actions::TemplateAction, case_util::CaseType, config::ConfigFile, constants::CONFIG_FILE,
+    template::TemplateCaseType,
+};
+use colored::Colorize;
+use std::{io::IsTerminal, path::Path};
+
+#[derive(Debug, Clone, Default)]
+pub struct TemplateInitOptions {
+    pub file_case: Option<CaseType>,
+    pub content_case: Option<CaseType>,
+    pub editor: Option<String>,
+    pub no_input: bool,
+}
+
+impl TemplateInitOptions {
+    fn is_empty(&self) -> bool {
+        self.file_case.is_none() && self.content_case.is_none() && self.editor.is_none()
+    }
+}
+
+pub struct TemplateInit;
+
+impl TemplateInit {
+    /**
+     * Write `_.json` config to the directory. Without flags, user is asked for case types
+     * and editor command, with flags only given values are changed in existing config.
+     */
+    pub fn init(directory: &Path, options: &TemplateInitOptions) {
+        let mut config = ConfigFile::load_config(directory);
+        let is_interactive = !options.no_input && std::io::stdin().is_terminal();
+
+        if options.is_empty() && is_interactive {
+            config.case_type = TemplateAction::get_template_config().case_type;
+            config.open_editor_command = TemplateAction::get_template_command_args();
+        } else {
+            let mut case_type = config.case_type.unwrap_or_else(TemplateCaseType::new);
+            if let Some(file_case) = &options.file_case {
+                case_type.file = file_case.to_owned();
+            }
+            if let Some(content_case) = &options.content_case {
+                case_type.content = content_case.to_owned();
+            }
+            config.case_type = Some(case_type);
+            if let Some(editor) = &options.editor {
+                config.open_editor_command =
+                    Some(editor.to_owned()).filter(|item| !item.is_empty());
+            }
+        }
+
+        if let Err(error) = config.save_config(directory) {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        println!();
+        println!(
+            "{} {}",
+            "✅ Config saved to".green(),
+            directory.join(CONFIG_FILE).to_str().unwrap().bold()
+        );
+    }
+
+    /**
+     * Parse value of `--file-case` / `--content-case` flag.
+     */
+    pub fn parse_case_type(value: &str) -> Result<CaseType, String> {
+        let case_type = CaseType::from_argument(value);
+        if case_type.is_not_unknown() {
+            return Ok(case_type);
+        }
+        let names = CaseType::all()
+            .iter()
+            .map(|case_type| case_type.to_str_name().to_string())
+            .collect::<Vec<_>>();
+        Err(format!("available case types: {}", names.join(", ")))
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{actions::TemplateFetch, template::TemplateFolder, template_git::TemplateGit};
+use colored::Colorize;
+use loading::Loading;
+use std::fs;
+
+pub struct TemplatePublishOptions {
+    /// git url or path of repository with shared templates
+    pub to: String,
+    pub message: Option<String>,
+}
+
+pub struct TemplatePublish;
+
+impl TemplatePublish {
+    /**
+     * Copy template to `.gen/<template>` of the git repository, commit and push it,
+     * so others can fetch it with `gen fetch`.
+     */
+    pub fn publish(template_folder: &TemplateFolder, options: &TemplatePublishOptions) {
+        let message = options
+            .message
+            .to_owned()
+            .unwrap_or_else(|| format!("Publish template {}", template_folder.name));
+        let result = TemplatePublish::push(&template_folder.name, &options.to, &message, |path| {
+            TemplateFetch::copy_template(&template_folder.path, path)
+        });
+        TemplatePublish::print_result(result, &template_folder.name, &options.to, "published");
+    }
+
+    /**
+     * Remove template from `.gen/<template>` of the git repository, commit and push it.
+     */
+    pub fn unpublish(template_name: &str, options: &TemplatePublishOptions) {
+        if let Err(error) = TemplateFolder::validate_name(template_name) {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        let message = options
+            .message
+            .to_owned()
+            .unwrap_or_else(|| format!("Unpublish template {}", template_name));
+        let result = TemplatePublish::push(template_name, &options.to, &message, |path| {
+            if !path.is_dir() {
+                return Err(format!(
+                    "Template {} is not published in {}",
+                    template_name, options.to
+                ));
+            }
+            fs::remove_dir_all(path).map_err(|error| error.to_string())
+        });
+        TemplatePublish::print_result(result, template_name, &options.to, "unpublished");
+    }
+
+    fn push<F>(template_name: &str, to: &str, message: &str, change: F) -> Result<bool, String>
+    where
+        F: FnOnce(&std::path::Path) -> Result<(), String>,
+    {
+        let loading = Loading::default();
+        loading.text(format!("Pushing {} to {}...", template_name, to).blue());
+        let result = TemplateGit::commit_and_push(to, template_name, message, change);
+        loading.end();
+        result.map(|commit| commit.is_some())
+    }
+
+    fn print_result(result: Result<bool, String>, template_name: &str, to: &str, action: &str) {
+        println!();
+        match result {
+            Ok(true) => println!(
+                "{} {} {} {}",
+                format!("✅ Template {}:", action).green(),
+                template_name.green().bold(),
+                "in".green(),
+                to
+            ),
+            Ok(false) => println!(
+                "{} {}",
+                "🤷 Nothing changed, template is already up to date in".yellow(),
+                to
+            ),
+            Err(error) => {
+                println!("{}", format!("🚨 {}", error).red());
+                std::process::exit(1);
+            }
+        }
+        println!();
+    }
+}
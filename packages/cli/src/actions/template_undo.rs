@@ -0,0 +1,506 @@
+use crate::{
+    actions::{TemplateUse, TemplateUseOptions},
+    cli_commands::CliCommands,
+    config::Config,
+    search_folder::SearchFolder,
+    template::TemplateFolder,
+    template_case::TemplateCaseResolver,
+    template_manifest::{TemplateManifest, TemplateManifestFile},
+    template_plan::{TemplatePlan, TemplatePlanAction},
+};
+use colored::Colorize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct TemplateUndo;
+
+impl TemplateUndo {
+    pub fn undo_last() {
+        let cwd = std::env::current_dir().unwrap();
+        let manifest = TemplateManifest::load_last(&cwd);
+        if manifest.is_none() {
+            println!();
+            println!("{}", "🚨 There is nothing to undo.".red());
+            println!();
+            std::process::exit(1);
+        }
+        let manifest = manifest.unwrap();
+
+        println!();
+        println!(
+            "Undoing last generation of template: {}",
+            manifest.template.green().bold()
+        );
+        println!();
+        TemplateUndo::revert_and_print(&cwd, &manifest);
+        manifest.remove();
+    }
+
+    /**
+     * Remove previously generated instance of template. Values are resolved same as for use,
+     * then the newest generation manifest with same template & values is reverted.
+     * When there is no manifest (generated before manifests existed), rendered files which
+     * still have the rendered content are removed after confirmation.
+     */
+    pub fn destroy(
+        local_config: &Config,
+        global_config: &Config,
+        template_folder: &TemplateFolder,
+        options: &TemplateUseOptions,
+    ) {
+        let result = SearchFolder::search(&template_folder.path);
+        let values_for_keys = TemplateUse::resolve_values(template_folder, &result, options);
+        if let Err(missing) = values_for_keys {
+            TemplateUse::print_missing_values(&missing);
+            std::process::exit(1);
+        }
+        let values_for_keys = values_for_keys.unwrap();
+        // auto values like `date` differ between runs, so they are not matched
+        let auto_keys = result
+            .variables
+            .iter()
+            .filter(|(_, variable)| variable.is_auto)
+            .map(|(key, _)| key.to_owned())
+            .collect::<Vec<_>>();
+
+        let cwd = std::env::current_dir().unwrap();
+        let manifest = TemplateUndo::find_manifest(
+            TemplateManifest::load_all(&cwd),
+            &template_folder.name,
+            &values_for_keys,
+            &auto_keys,
+        );
+
+        println!();
+        println!(
+            "Destroying instance of template: {}",
+            template_folder.name.green().bold()
+        );
+        println!();
+
+        if let Some(manifest) = manifest {
+            TemplateUndo::revert_and_print(&cwd, &manifest);
+            manifest.remove();
+            return;
+        }
+
+        println!(
+            "{}",
+            "No generation record found, removing rendered files instead.".yellow()
+        );
+        println!();
+        let case_resolver = TemplateCaseResolver::new(
+            &result.template_config,
+            Some(&local_config.config),
+            &global_config.config,
+        );
+        let plan = TemplateUse::create_plan(&case_resolver, &result, &values_for_keys, &cwd)
+            .and_then(|mut plan| plan.register_files().map(|_| plan));
+        if let Err(error) = plan {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        let plan = plan.unwrap();
+        let (manifest, skipped) = TemplateUndo::manifest_from_rendered_plan(&cwd, &plan);
+        for path in skipped.iter() {
+            println!(
+                "{} {}",
+                "🚨 Skipping file, it differs from rendered template:".yellow(),
+                path
+            );
+        }
+        if manifest.files.is_empty() {
+            println!();
+            println!("{}", "🤷 Nothing to remove.".yellow());
+            println!();
+            return;
+        }
+        if !options.no_input {
+            for file in manifest.files.iter() {
+                let action = match file.action {
+                    TemplatePlanAction::Append => "Strip",
+                    _ => "Remove",
+                };
+                println!("{} file: {}", action, file.path.green());
+            }
+            println!();
+            if !CliCommands::confirm("Do you want to continue?") {
+                std::process::exit(1);
+            }
+            println!();
+        }
+        TemplateUndo::revert_and_print(&cwd, &manifest);
+    }
+
+    /**
+     * Newest manifest of the template with same values, auto values are ignored.
+     */
+    fn find_manifest(
+        manifests: Vec<TemplateManifest>,
+        template_name: &str,
+        values_for_keys: &HashMap<String, String>,
+        auto_keys: &[String],
+    ) -> Option<TemplateManifest> {
+        let without_auto = |values: &mut dyn Iterator<Item = (&String, &String)>| {
+            values
+                .filter(|(key, _)| !auto_keys.contains(key))
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let values = without_auto(&mut values_for_keys.iter());
+        manifests.into_iter().rev().find(|manifest| {
+            manifest.template == template_name
+                && without_auto(&mut manifest.values.iter()) == values
+        })
+    }
+
+    /**
+     * Manifest which removes rendered files, file is removed only when its content is same
+     * as the rendered one, so hand written files are never removed. Returns skipped paths too.
+     */
+    fn manifest_from_rendered_plan(
+        cwd: &Path,
+        plan: &TemplatePlan,
+    ) -> (TemplateManifest, Vec<String>) {
+        let mut directories = Vec::new();
+        let mut skipped = Vec::new();
+        let files = plan
+            .files
+            .iter()
+            // merged keys & declarations cannot be told apart from ones which were there before
+            .filter(|file| {
+                !matches!(
+                    file.action,
+                    TemplatePlanAction::Create
+                        | TemplatePlanAction::Merge
+                        | TemplatePlanAction::Register
+                )
+            })
+            .filter_map(|file| {
+                let path = TemplatePlan::display_path(&file.path, cwd);
+                if file.action == TemplatePlanAction::Append {
+                    let existing_content = file.existing_content.as_deref().unwrap_or_default();
+                    if file.content.is_empty() || !existing_content.contains(&file.content) {
+                        skipped.push(path);
+                        return None;
+                    }
+                    return Some(TemplateManifestFile {
+                        path,
+                        action: TemplatePlanAction::Append,
+                        appended: Some(file.content.to_owned()),
+                        backup: None,
+                    });
+                }
+                if file.existing_content.as_deref() != Some(file.final_content().as_str()) {
+                    skipped.push(path);
+                    return None;
+                }
+
+                // existing file which is not appended was rendered by template, so it's removed
+                // together with folders which become empty
+                for directory in file.path.ancestors().skip(1) {
+                    if directory == cwd || !directory.starts_with(cwd) {
+                        break;
+                    }
+                    let directory = TemplatePlan::display_path(directory, cwd);
+                    if !directories.contains(&directory) {
+                        directories.push(directory);
+                    }
+                }
+                Some(TemplateManifestFile {
+                    path,
+                    action: TemplatePlanAction::Create,
+                    appended: None,
+                    backup: None,
+                })
+            })
+            .collect();
+        // deepest folders have to be removed first
+        directories.sort_by_key(|directory| directory.len());
+
+        let manifest = TemplateManifest {
+            id: "".to_string(),
+            template: "".to_string(),
+            values: BTreeMap::new(),
+            files,
+            directories,
+            history_path: PathBuf::new(),
+        };
+        (manifest, skipped)
+    }
+
+    fn revert_and_print(cwd: &Path, manifest: &TemplateManifest) {
+        let warnings = TemplateUndo::revert(cwd, manifest);
+        for file in manifest.files.iter() {
+            let action = match file.action {
+                TemplatePlanAction::Create => "Removed",
+                TemplatePlanAction::Overwrite
+                | TemplatePlanAction::Merge
+                | TemplatePlanAction::Register => "Restored",
+                TemplatePlanAction::Append => "Stripped",
+            };
+            println!("{} file: {}", action, file.path.green());
+        }
+        for warning in warnings.iter() {
+            println!("{}", format!("🚨 {}", warning).yellow());
+        }
+        println!();
+        println!("{}", "Done!".green());
+    }
+
+    /**
+     * Reverse all file changes from the manifest, returns list of warnings for files
+     * which could not be reverted.
+     */
+    pub fn revert(cwd: &Path, manifest: &TemplateManifest) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let backup_path = manifest.backup_path();
+
+        for file in manifest.files.iter().rev() {
+            let path = cwd.join(&file.path);
+            let result = match file.action {
+                TemplatePlanAction::Create => {
+                    if !path.exists() {
+                        warnings.push(format!("File {} does not exist anymore.", file.path));
+                        continue;
+                    }
+                    fs::remove_file(&path)
+                }
+                TemplatePlanAction::Overwrite
+                | TemplatePlanAction::Merge
+                | TemplatePlanAction::Register => {
+                    let backup = backup_path.join(file.backup.as_ref().unwrap());
+                    fs::copy(backup, &path).map(|_| ())
+                }
+                TemplatePlanAction::Append => {
+                    let appended = file.appended.as_ref().unwrap();
+                    let content = fs::read_to_string(&path).unwrap_or_default();
+                    let index = content.rfind(appended.as_str());
+                    if index.is_none() || appended.is_empty() {
+                        warnings.push(format!(
+                            "Appended content not found in {}, skipping.",
+                            file.path
+                        ));
+                        continue;
+                    }
+                    let index = index.unwrap();
+                    let new_content = format!(
+                        "{}{}",
+                        &content[..index],
+                        &content[index + appended.len()..]
+                    );
+                    fs::write(&path, new_content)
+                }
+            };
+            if let Err(error) = result {
+                warnings.push(format!("Cannot revert {}: {}", file.path, error));
+            }
+        }
+
+        for directory in manifest.directories.iter().rev() {
+            // only empty folders are removed
+            let _ = fs::remove_dir(cwd.join(directory));
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateUndo;
+    use crate::{
+        actions::TemplateUse,
+        template_git::TemplateGit,
+        template_manifest::TemplateManifest,
+        template_merge::TemplateMerge,
+        template_plan::{TemplatePlan, TemplatePlanAction, TemplatePlanFile},
+    };
+    use std::{collections::HashMap, fs};
+
+    #[test]
+    fn should_undo_generation() {
+        let cwd = TemplateGit::temp_directory("test-undo").unwrap();
+        fs::create_dir_all(cwd.join(".gen")).unwrap();
+        fs::create_dir_all(cwd.join("src")).unwrap();
+        fs::write(cwd.join("README.md"), "readme\n").unwrap();
+        fs::write(cwd.join("index.ts"), "export * from './a';\n").unwrap();
+        fs::write(cwd.join("package.json"), "{\n  \"name\": \"app\"\n}\n").unwrap();
+        fs::write(cwd.join("src/lib.rs"), "pub mod a;\n").unwrap();
+
+        let mut plan = TemplatePlan::new();
+        plan.files.push(TemplatePlanFile::new(
+            cwd.join("README.md"),
+            "generated\n".to_string(),
+            false,
+        ));
+        plan.files.push(TemplatePlanFile::new(
+            cwd.join("index.ts"),
+            "export * from './b';\n".to_string(),
+            true,
+        ));
+        plan.files.push(
+            TemplatePlanFile::new(
+                cwd.join("package.json"),
+                "{ \"scripts\": { \"b\": \"b\" } }".to_string(),
+                false,
+            )
+            .with_merge(TemplateMerge::Auto)
+            .unwrap(),
+        );
+        plan.files.push(TemplatePlanFile::new(
+            cwd.join("src/b.rs"),
+            "pub struct B;\n".to_string(),
+            false,
+        ));
+        plan.register(&cwd.join("src/b.rs")).unwrap();
+        plan.files.push(TemplatePlanFile::new(
+            cwd.join("new/deep/file.txt"),
+            "file".to_string(),
+            false,
+        ));
+        let actions = plan
+            .files
+            .iter()
+            .map(|file| file.action)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                TemplatePlanAction::Overwrite,
+                TemplatePlanAction::Append,
+                TemplatePlanAction::Merge,
+                TemplatePlanAction::Create,
+                TemplatePlanAction::Register,
+                TemplatePlanAction::Create,
+            ]
+        );
+
+        let manifest = TemplateUse::apply_plan(&cwd, "test", &HashMap::new(), &plan)
+            .unwrap()
+            .unwrap();
+        assert_eq!(manifest.directories, vec!["new", "new/deep"]);
+        assert_eq!(
+            fs::read_to_string(cwd.join("src/lib.rs")).unwrap(),
+            "pub mod a;\npub mod b;\n"
+        );
+        assert!(fs::read_to_string(cwd.join("package.json"))
+            .unwrap()
+            .contains("\"scripts\""));
+
+        let manifest = TemplateManifest::load_last(&cwd).unwrap();
+        assert!(TemplateUndo::revert(&cwd, &manifest).is_empty());
+        manifest.remove();
+        assert_eq!(
+            fs::read_to_string(cwd.join("README.md")).unwrap(),
+            "readme\n"
+        );
+        assert_eq!(
+            fs::read_to_string(cwd.join("index.ts")).unwrap(),
+            "export * from './a';\n"
+        );
+        assert_eq!(
+            fs::read_to_string(cwd.join("package.json")).unwrap(),
+            "{\n  \"name\": \"app\"\n}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(cwd.join("src/lib.rs")).unwrap(),
+            "pub mod a;\n"
+        );
+        assert!(!cwd.join("src/b.rs").exists());
+        assert!(!cwd.join("new").exists());
+        assert!(TemplateManifest::load_all(&cwd).is_empty());
+        fs::remove_dir_all(&cwd).unwrap();
+    }
+
+    #[test]
+    fn should_not_record_empty_plan() {
+        let cwd = TemplateGit::temp_directory("test-undo-empty").unwrap();
+        fs::create_dir_all(cwd.join(".gen")).unwrap();
+        let manifest = TemplateUse::apply_plan(&cwd, "test", &HashMap::new(), &TemplatePlan::new());
+        assert!(manifest.unwrap().is_none());
+        assert!(TemplateManifest::load_all(&cwd).is_empty());
+        fs::remove_dir_all(&cwd).unwrap();
+    }
+
+    #[test]
+    fn should_match_destroy_manifest() {
+        let cwd = TemplateGit::temp_directory("test-destroy").unwrap();
+        fs::create_dir_all(cwd.join(".gen")).unwrap();
+        let mut values = HashMap::new();
+        values.insert("__var___".to_string(), "button".to_string());
+        values.insert("__var___date".to_string(), "2024-01-01".to_string());
+        let mut plan = TemplatePlan::new();
+        plan.files.push(TemplatePlanFile::new(
+            cwd.join("button.ts"),
+            "button".to_string(),
+            false,
+        ));
+        TemplateUse::apply_plan(&cwd, "component", &values, &plan).unwrap();
+
+        // generated on another day
+        values.insert("__var___date".to_string(), "2024-01-02".to_string());
+        let auto_keys = vec!["__var___date".to_string()];
+        let find = |template_name: &str, values: &HashMap<String, String>, auto_keys: &[String]| {
+            TemplateUndo::find_manifest(
+                TemplateManifest::load_all(&cwd),
+                template_name,
+                values,
+                auto_keys,
+            )
+        };
+        assert!(find("component", &values, &auto_keys).is_some());
+        assert!(find("component", &values, &[]).is_none());
+        assert!(find("other", &values, &auto_keys).is_none());
+        values.insert("__var___".to_string(), "input".to_string());
+        assert!(find("component", &values, &auto_keys).is_none());
+        fs::remove_dir_all(&cwd).unwrap();
+    }
+
+    #[test]
+    fn should_remove_only_rendered_files() {
+        let cwd = TemplateGit::temp_directory("test-destroy-rendered").unwrap();
+        fs::create_dir_all(cwd.join("components/button")).unwrap();
+        fs::write(cwd.join("components/button/button.ts"), "button").unwrap();
+        fs::write(cwd.join("components/notes.md"), "hand written").unwrap();
+        fs::write(cwd.join("index.ts"), "export * from './a';\n").unwrap();
+        fs::write(cwd.join("main.ts"), "main\n").unwrap();
+
+        let mut plan = TemplatePlan::new();
+        for (path, content, is_append_mode) in [
+            ("components/button/button.ts", "button", false),
+            ("components/notes.md", "notes", false),
+            ("index.ts", "export * from './a';\n", true),
+            ("main.ts", "export * from './b';\n", true),
+            ("missing.ts", "missing", false),
+        ] {
+            plan.files.push(TemplatePlanFile::new(
+                cwd.join(path),
+                content.to_string(),
+                is_append_mode,
+            ));
+        }
+
+        let (manifest, skipped) = TemplateUndo::manifest_from_rendered_plan(&cwd, &plan);
+        let paths = manifest
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["components/button/button.ts", "index.ts"]);
+        assert_eq!(skipped, vec!["components/notes.md", "main.ts"]);
+        assert_eq!(
+            manifest.directories,
+            vec!["components", "components/button"]
+        );
+
+        assert!(TemplateUndo::revert(&cwd, &manifest).is_empty());
+        assert!(!cwd.join("components/button").exists());
+        assert!(cwd.join("components/notes.md").exists());
+        assert_eq!(fs::read_to_string(cwd.join("index.ts")).unwrap(), "");
+        assert_eq!(fs::read_to_string(cwd.join("main.ts")).unwrap(), "main\n");
+        fs::remove_dir_all(&cwd).unwrap();
+    }
+}
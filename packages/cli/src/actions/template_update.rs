@@ -0,0 +1,177 @@
+use crate::{
+    actions::TemplateFetch,
+    config::Config,
+    template_git::TemplateGit,
+    template_lock::{TemplateLock, TemplateLockEntry},
+    template_plan::{TemplatePlan, TemplatePlanFile},
+    template_source::TemplateSource,
+};
+use colored::Colorize;
+use loading::Loading;
+use std::{fs, path::Path};
+
+pub struct TemplateUpdate;
+
+impl TemplateUpdate {
+    /**
+     * Fetch templates recorded in gen.lock again and replace local copies with newest version.
+     * Recorded ref is fetched, so only branches move, `git_ref` switches template to another ref.
+     * Templates edited since they were fetched are kept, unless `force` is set.
+     */
+    pub fn update(
+        config: &Config,
+        template_name: Option<&str>,
+        git_ref: Option<&str>,
+        force: bool,
+    ) {
+        let mut lock = TemplateLock::load(&config.path).unwrap_or_else(|error| {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        });
+
+        let template_names = match template_name {
+            Some(template_name) if !lock.templates.contains_key(template_name) => {
+                println!(
+                    "{} {}",
+                    "🚨 Template was not fetched, it is missing in gen.lock:".red(),
+                    template_name
+                );
+                std::process::exit(1);
+            }
+            Some(template_name) => vec![template_name.to_string()],
+            None => lock.templates.keys().cloned().collect(),
+        };
+        if template_names.is_empty() {
+            println!();
+            println!(
+                "{}",
+                "🤷 There are no fetched templates to update.".yellow()
+            );
+            println!();
+            return;
+        }
+
+        let mut is_ok = true;
+        for template_name in template_names.iter() {
+            let mut entry = lock.templates.get(template_name).unwrap().to_owned();
+            if let Some(git_ref) = git_ref {
+                entry.git_ref = Some(git_ref.to_string());
+            }
+            println!();
+            let result = TemplateUpdate::update_template(config, template_name, &entry, force);
+            match result {
+                Ok(entry) => {
+                    lock.templates.insert(template_name.to_owned(), entry);
+                }
+                Err(error) => {
+                    println!("{} {}", format!("🚨 {}:", template_name).red(), error.red());
+                    is_ok = false;
+                }
+            }
+        }
+
+        if let Err(error) = lock.save(&config.path) {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        println!();
+        if !is_ok {
+            std::process::exit(1);
+        }
+    }
+
+    fn update_template(
+        config: &Config,
+        template_name: &str,
+        entry: &TemplateLockEntry,
+        force: bool,
+    ) -> Result<TemplateLockEntry, String> {
+        let template_path = config.path.join(template_name);
+        let local_hash = if template_path.exists() {
+            Some(TemplateLock::hash_folder(&template_path)?)
+        } else {
+            None
+        };
+        let is_edited = local_hash.as_ref().is_some_and(|hash| hash != &entry.hash);
+        if is_edited && !force {
+            return Err(
+                "template was edited locally, use --force to overwrite local changes".to_string(),
+            );
+        }
+
+        let fetch_path = TemplateGit::temp_directory("update")?;
+        let loading = Loading::default();
+        loading.text(format!("Fetching {}...", entry.source).blue());
+        let result =
+            TemplateSource::parse(&entry.source).fetch(entry.git_ref.as_deref(), &fetch_path);
+        loading.end();
+
+        let result = result.and_then(|fetched_source| {
+            let fetched_path = fetched_source.root.join(&entry.subdir);
+            if !fetched_path.is_dir() {
+                return Err(format!("{} not found in {}", entry.subdir, entry.source));
+            }
+            let hash = TemplateLock::hash_folder(&fetched_path)?;
+            let updated_entry = TemplateLockEntry {
+                commit: fetched_source.commit.to_owned(),
+                hash: hash.to_owned(),
+                ..entry.to_owned()
+            };
+            if local_hash.as_ref() == Some(&hash) {
+                println!("{} {}", "✅ Up to date:".green(), template_name.bold());
+                return Ok(updated_entry);
+            }
+
+            println!("{} {}", "🔄 Updating:".blue(), template_name.bold());
+            println!();
+            let cwd = config.path.parent().unwrap_or(&config.path);
+            TemplateUpdate::print_changes(&fetched_path, &template_path, cwd)?;
+            TemplateFetch::copy_template(&fetched_path, &template_path)?;
+            println!("{} {}", "✅ Updated:".green(), template_name.bold());
+            Ok(updated_entry)
+        });
+        let _ = fs::remove_dir_all(&fetch_path);
+        result
+    }
+
+    /**
+     * Created / overwritten files with diff, removed files are listed after them.
+     */
+    fn print_changes(fetched_path: &Path, template_path: &Path, cwd: &Path) -> Result<(), String> {
+        let fetched_files = TemplateLock::list_files(fetched_path)?;
+        let local_files = if template_path.exists() {
+            TemplateLock::list_files(template_path)?
+        } else {
+            Vec::new()
+        };
+
+        let mut plan = TemplatePlan::new();
+        for file in fetched_files.iter() {
+            let content = fs::read(fetched_path.join(file)).map_err(|error| error.to_string())?;
+            let local_content = fs::read(template_path.join(file)).ok();
+            if local_content.as_ref() == Some(&content) {
+                continue;
+            }
+            // binary files are listed without diff
+            let content = String::from_utf8(content).unwrap_or_default();
+            plan.files.push(TemplatePlanFile::new(
+                template_path.join(file),
+                content,
+                false,
+            ));
+        }
+        plan.print_changes(cwd);
+
+        let width = 10;
+        for file in local_files.iter() {
+            if !fetched_files.contains(file) {
+                println!(
+                    "{:width$} {}",
+                    "delete".red().bold(),
+                    TemplatePlan::display_path(&template_path.join(file), cwd)
+                );
+            }
+        }
+        Ok(())
+    }
+}
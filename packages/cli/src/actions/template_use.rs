@@ -1,160 +1,576 @@
 use crate::{
-    cli_commands::CliCommands, config::Config, search_folder::SearchFolder,
-    template::TemplateFolder, template_variable::TemplateVariableInfo,
+    cli_commands::CliCommands,
+    config::Config,
+    search_folder::{SearchFolder, SearchItem, SearchResult},
+    template::TemplateFolder,
+    template_answers::TemplateAnswers,
+    template_auto::{TemplateAuto, TemplateAutoContext},
+    template_case::TemplateCaseResolver,
+    template_manifest::TemplateManifest,
+    template_plan::{TemplatePlan, TemplatePlanAction, TemplatePlanFile},
+    template_render::TemplateRender,
+    template_variable::{TemplateVariable, TemplateVariableInfo},
 };
 use colored::Colorize;
-use std::{collections::HashMap, fs, io::Write};
+use std::{collections::HashMap, io::IsTerminal, path::Path};
+
+#[derive(Debug, Clone, Default)]
+pub struct TemplateUseOptions {
+    pub answers: TemplateAnswers,
+    /// positional name, it's the value of unnamed `__var__`
+    pub name: Option<String>,
+    pub no_input: bool,
+    /// existing files are overwritten without asking
+    pub overwrite: bool,
+    pub dry_run: bool,
+}
 
 pub struct TemplateUse;
 
 impl TemplateUse {
-    fn get_case_value(
-        is_file_path: bool,
-        config: &Config,
-        variable: &TemplateVariableInfo,
-        value: &str,
-    ) -> String {
-        if variable.case_type.is_not_unknown() {
-            return variable.case_type.from_string_to_case(value);
-        };
-
-        let case_type_from_config = if is_file_path {
-            &config.config.case_type.file
-        } else {
-            &config.config.case_type.content
-        };
+    /**
+     * Resolve values for all template variables. Values from answers are used first,
+     * then user is prompted for the rest. When prompting is not possible (--no-input or no TTY),
+     * list of unresolved variable keys is returned as error.
+     */
+    pub fn resolve_values(
+        template_folder: &TemplateFolder,
+        result: &SearchResult,
+        options: &TemplateUseOptions,
+    ) -> Result<HashMap<String, String>, Vec<String>> {
+        let mut values_for_keys = HashMap::new();
+        let mut missing = Vec::new();
+        let select_options = result.template_config.select_options.as_ref();
 
-        if case_type_from_config.is_not_unknown() {
-            return case_type_from_config.from_string_to_case(value);
+        for (key, variable) in result.variables.iter() {
+            let answer = options.answers.get(variable);
+            if answer.is_none() {
+                continue;
+            }
+            let mut answer = answer.unwrap().to_owned();
+            if variable.template_variable.is_bool() {
+                let value = TemplateRender::parse_bool(&answer);
+                if value.is_none() {
+                    println!(
+                        "{} {} {} {}",
+                        "🚨 Invalid value".red(),
+                        answer.bold(),
+                        "for".red(),
+                        TemplateAnswers::key_hint(variable).bold()
+                    );
+                    println!("Available options: {}", "true, false".cyan());
+                    std::process::exit(1);
+                }
+                answer = value.unwrap().to_string();
+            }
+            if variable.template_variable.is_list() {
+                answer = TemplateRender::join_list(&TemplateRender::split_list(&answer));
+            }
+            if variable.template_variable.is_select() {
+                let options = select_options.and_then(|item| item.get(&variable.raw_value));
+                if let Some(options) = options {
+                    if !options.contains(&answer) {
+                        println!(
+                            "{} {} {} {}",
+                            "🚨 Invalid value".red(),
+                            answer.bold(),
+                            "for".red(),
+                            TemplateAnswers::key_hint(variable).bold()
+                        );
+                        println!("Available options: {}", options.join(", ").cyan());
+                        std::process::exit(1);
+                    }
+                }
+            }
+            values_for_keys.insert(key.to_owned(), answer);
         }
 
-        value.to_string()
-    }
-
-    pub fn use_it(global_config: &Config, _config: &Config, template_folder: &TemplateFolder) {
-        let result = SearchFolder::search(&template_folder.path);
-        println!();
-        println!("Using template: {}", template_folder.name.green().bold());
-        if !result.template_config.description.is_empty() {
-            println!();
-            println!("Description: {}", result.template_config.description);
-        }
-        println!();
-        let mut values_for_keys = HashMap::new();
+        let is_interactive = !options.no_input && std::io::stdin().is_terminal();
+        for (key, variable) in result.variables.iter() {
+            if values_for_keys.contains_key(key) || variable.is_auto {
+                continue;
+            }
+            let options = select_options.and_then(|item| item.get(&variable.raw_value));
+            if variable.template_variable.is_select() && options.is_none() {
+                continue;
+            }
+            if !is_interactive {
+                missing.push(TemplateAnswers::key_hint(variable));
+                continue;
+            }
 
-        for (key, variable) in result.variables {
-            let var_name = SearchFolder::get_key(&variable);
+            let var_name = SearchFolder::get_key(variable);
             if variable.template_variable.is_var() {
-                let text = format!("Add value for {}", &var_name.green().bold());
+                let text = format!("Add value for {}", var_name.green().bold());
                 let result = CliCommands::input_not_empty(&text, "Please provide some value", None);
                 if result.is_err() {
                     std::process::exit(1);
                 }
                 let result = result.unwrap();
-                values_for_keys.insert(key, result);
+                values_for_keys.insert(key.to_owned(), result);
                 println!();
-            } else if variable.template_variable.is_select() {
-                let text = format!("Select option for {}", &var_name.green().bold());
-                if let Some(options_map) = &result.template_config.select_options {
-                    if let Some(options) = options_map.get(&variable.raw_value) {
-                        let result = CliCommands::select(&text, options);
-                        if result.is_err() {
-                            std::process::exit(1);
-                        }
-                        let result = result.unwrap();
-                        values_for_keys.insert(key, result);
-                        println!();
-                    }
+            } else if variable.template_variable.is_list() {
+                let text = format!("Add comma separated values for {}", var_name.green().bold());
+                let result = CliCommands::input(&text, None);
+                if result.is_err() {
+                    std::process::exit(1);
+                }
+                let items = TemplateRender::split_list(&result.unwrap());
+                values_for_keys.insert(key.to_owned(), TemplateRender::join_list(&items));
+                println!();
+            } else if variable.template_variable.is_bool() {
+                let text = format!("Enable {}?", var_name.green().bold());
+                let result = CliCommands::confirm(&text);
+                values_for_keys.insert(key.to_owned(), result.to_string());
+                println!();
+            } else if let Some(options) = options {
+                let text = format!("Select option for {}", var_name.green().bold());
+                let result = CliCommands::select(&text, options);
+                if result.is_err() {
+                    std::process::exit(1);
                 }
+                let result = result.unwrap();
+                values_for_keys.insert(key.to_owned(), result);
+                println!();
+            }
+        }
+
+        TemplateUse::resolve_auto_values(template_folder, result, &mut values_for_keys);
+        for (key, variable) in result.variables.iter() {
+            if !values_for_keys.contains_key(key) && variable.is_auto {
+                missing.push(TemplateAnswers::key_hint(variable));
             }
         }
 
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        Ok(values_for_keys)
+    }
+
+    /**
+     * Auto variables can depend on each other, so they are resolved in passes until nothing changes.
+     */
+    fn resolve_auto_values(
+        template_folder: &TemplateFolder,
+        result: &SearchResult,
+        values_for_keys: &mut HashMap<String, String>,
+    ) {
         let cwd = std::env::current_dir().unwrap();
+        let context = TemplateAutoContext::new(&cwd, &template_folder.name);
+        loop {
+            let mut is_changed = false;
+            for (key, variable) in result.variables.iter() {
+                if !variable.is_auto || values_for_keys.contains_key(key) {
+                    continue;
+                }
+                let value = TemplateAuto::resolve(
+                    variable,
+                    &result.template_config,
+                    &context,
+                    values_for_keys,
+                );
+                if let Some(value) = value {
+                    values_for_keys.insert(key.to_owned(), value);
+                    is_changed = true;
+                }
+            }
+            if !is_changed {
+                break;
+            }
+        }
+    }
+
+    /**
+     * Render all template files with resolved values, nothing is written to disk.
+     * Files with false `__if__` condition in path, or with content which is empty after
+     * conditional blocks are rendered, are not generated.
+     * Files with `register` are declared in module files by `TemplatePlan::register_files`.
+     */
+    pub fn create_plan(
+        case_resolver: &TemplateCaseResolver,
+        result: &SearchResult,
+        values_for_keys: &HashMap<String, String>,
+        cwd: &Path,
+    ) -> Result<TemplatePlan, String> {
+        let mut plan = TemplatePlan::new();
         for file in result.files.iter() {
             if file.is_config {
                 continue;
             }
-            let content = std::fs::read_to_string(&file.path).unwrap();
-            let mut new_content = content.clone();
+            let file_config = result.template_config.file_config(&file.template_path);
+            let insert = file_config.and_then(|file_config| file_config.insert.to_owned());
+            let dedupe = file_config
+                .and_then(|file_config| file_config.dedupe)
+                .unwrap_or_default();
+            let merge = file_config.and_then(|file_config| file_config.merge);
+            let register = file_config
+                .and_then(|file_config| file_config.register)
+                .unwrap_or_default();
+            for values_for_keys in TemplateUse::get_path_variants(file, values_for_keys)? {
+                let plan_file =
+                    TemplateUse::create_plan_file(case_resolver, file, &values_for_keys, cwd)?;
+                let plan_file = match (plan_file, merge) {
+                    (Some(plan_file), Some(merge)) => Some(plan_file.with_merge(merge)?),
+                    (Some(plan_file), _)
+                        if insert.is_some() || plan_file.action == TemplatePlanAction::Append =>
+                    {
+                        let insert = insert.to_owned().unwrap_or_default();
+                        Some(plan_file.with_insert(insert, dedupe)?)
+                    }
+                    (plan_file, _) => plan_file,
+                };
+                if let Some(mut plan_file) = plan_file {
+                    plan_file.register = register;
+                    plan.files.push(plan_file);
+                }
+            }
+        }
+        Ok(plan)
+    }
 
-            let mut replace_vec = Vec::new();
-            for variable in TemplateVariableInfo::parse_iter(&content) {
+    /**
+     * List variable in path generates one file per item, each file gets the item as list value
+     * together with `__index__`, `__if__first__` and `__if__last__` helpers.
+     */
+    fn get_path_variants(
+        file: &SearchItem,
+        values_for_keys: &HashMap<String, String>,
+    ) -> Result<Vec<HashMap<String, String>>, String> {
+        let mut list_keys = Vec::new();
+        for part in file.template_path.iter() {
+            for variable in TemplateVariableInfo::parse_iter(part.to_str().unwrap()) {
+                let variable =
+                    SearchFolder::resolve_name(variable, |key| values_for_keys.contains_key(key));
                 let key = SearchFolder::get_key(&variable);
-                if let Some(value) = values_for_keys.get(&key) {
-                    let case_value =
-                        TemplateUse::get_case_value(false, global_config, &variable, value);
-
-                    replace_vec.push((variable.raw_value.clone(), case_value));
+                if variable.template_variable.is_list() && !list_keys.contains(&key) {
+                    list_keys.push(key);
                 }
             }
+        }
+        if list_keys.is_empty() {
+            return Ok(vec![values_for_keys.clone()]);
+        }
+        if list_keys.len() > 1 {
+            return Err(format!(
+                "Only one list variable can be used in path {}",
+                file.path.to_str().unwrap()
+            ));
+        }
 
-            // need to sort by length to avoid replacing the wrong values
-            // it start with the longest value
-            replace_vec.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
-            for (old, new) in replace_vec {
-                new_content = new_content.replace(&old, &new);
-            }
+        let key = &list_keys[0];
+        let value = values_for_keys.get(key).map(|value| value.as_str());
+        let items = TemplateRender::split_list(value.unwrap_or_default());
+        let variants = (0..items.len())
+            .map(|index| TemplateRender::get_item_values(values_for_keys, key, &items, index))
+            .collect();
+        Ok(variants)
+    }
 
-            let mut new_path = Vec::new();
-            let mut is_append_mode = false;
+    fn create_plan_file(
+        case_resolver: &TemplateCaseResolver,
+        file: &SearchItem,
+        values_for_keys: &HashMap<String, String>,
+        cwd: &Path,
+    ) -> Result<Option<TemplatePlanFile>, String> {
+        let template_path = TemplateUse::render_path_conditions(file, values_for_keys)?;
+        if template_path.is_none() {
+            return Ok(None);
+        }
+        let template_path = template_path.unwrap();
 
-            let length = file.template_path.iter().count();
-            for (index, part) in file.template_path.iter().enumerate() {
-                let part = part.to_str().unwrap();
-                let is_last_part = index == length - 1;
-                if let Some(variable) = TemplateVariableInfo::from_str(part) {
-                    let key = SearchFolder::get_key(&variable);
-                    if let Some(value) = values_for_keys.get(&key) {
-                        let case_value =
-                            TemplateUse::get_case_value(true, global_config, &variable, value);
+        let content = std::fs::read_to_string(&file.path).unwrap();
+        let has_blocks = TemplateRender::has_blocks(&content);
+        let new_content = TemplateRender::render(&content, values_for_keys, case_resolver)
+            .map_err(|error| format!("{} in {}", error, file.path.to_str().unwrap()))?;
+        if has_blocks && new_content.trim().is_empty() {
+            return Ok(None);
+        }
 
-                        let new_part = part.replace(&variable.raw_value, &case_value);
+        let new_path = template_path
+            .iter()
+            .map(|part| {
+                TemplateRender::render_variables(part, values_for_keys, case_resolver, true)
+            })
+            .collect::<Vec<_>>();
+        // file name without any set variable is shared by all usages, so it's appended to
+        let is_append_mode = template_path.last().is_none_or(|part| {
+            !TemplateVariableInfo::parse_iter(part).any(|variable| {
+                let has_key = |key: &str| values_for_keys.contains_key(key);
+                has_key(&SearchFolder::get_key(&SearchFolder::resolve_name(
+                    variable, has_key,
+                )))
+            })
+        });
+        let separator = if cfg!(windows) { "\\" } else { "/" };
+        let path = cwd.join(new_path.join(separator));
 
-                        new_path.push(new_part.to_owned());
-                    } else {
-                        if is_last_part {
-                            is_append_mode = true;
-                        }
-                        new_path.push(part.to_string());
-                    }
-                } else {
-                    if is_last_part {
-                        is_append_mode = true;
-                    }
-                    new_path.push(part.to_string());
+        Ok(Some(TemplatePlanFile::new(
+            path,
+            new_content,
+            is_append_mode,
+        )))
+    }
+
+    /**
+     * Path segments can contain `__if__` conditions, for example `__if__bool__stories__/story.tsx`.
+     * Conditions are removed from the path, None is returned when any of them is not met.
+     */
+    fn render_path_conditions(
+        file: &SearchItem,
+        values_for_keys: &HashMap<String, String>,
+    ) -> Result<Option<Vec<String>>, String> {
+        let mut template_path = Vec::new();
+        for part in file.template_path.iter() {
+            let part = part.to_str().unwrap();
+            let mut new_part = String::new();
+            let mut last_index = 0;
+            for variable in TemplateVariableInfo::parse_iter(part) {
+                if !variable.template_variable.is_block() && !variable.template_variable.is_repeat()
+                {
+                    continue;
+                }
+                let condition = variable
+                    .condition
+                    .as_ref()
+                    .filter(|_| variable.template_variable == TemplateVariable::If);
+                if condition.is_none() {
+                    return Err(format!(
+                        "Only __if__ conditions are supported in paths, found {} in {}",
+                        variable.raw_value,
+                        file.path.to_str().unwrap()
+                    ));
+                }
+                if !TemplateRender::is_condition_met(condition.unwrap(), values_for_keys) {
+                    return Ok(None);
                 }
+                new_part.push_str(&part[last_index..variable.start_index]);
+                last_index = variable.end_index;
             }
-            let separator = if cfg!(windows) { "\\" } else { "/" };
-            let path = cwd.join(new_path.join(separator));
+            new_part.push_str(&part[last_index..]);
+            if !new_part.is_empty() {
+                template_path.push(new_part);
+            }
+        }
+        Ok(Some(template_path))
+    }
 
-            println!("Writing file: {}", path.to_str().unwrap().green());
+    pub fn print_missing_values(missing: &[String]) {
+        println!(
+            "{}",
+            "🚨 Missing values for template variables:".red().bold()
+        );
+        for key in missing {
+            println!("   {}", key.yellow());
+        }
+        println!();
+        println!(
+            "{}",
+            "Provide them with --set key=value or --answers file.".italic()
+        );
+    }
 
-            if let Some(parent) = path.parent() {
-                if !parent.exists() {
-                    fs::create_dir_all(parent).unwrap();
-                }
+    /**
+     * Ask for all overwrites before anything is written, declined files are removed from the plan.
+     * Without input nothing can be confirmed, so existing files fail the command unless
+     * `overwrite` is set.
+     */
+    fn confirm_overwrites(
+        plan: TemplatePlan,
+        options: &TemplateUseOptions,
+        cwd: &Path,
+    ) -> TemplatePlan {
+        let existing_files = plan
+            .files
+            .iter()
+            .filter(|file| file.action == TemplatePlanAction::Overwrite)
+            .collect::<Vec<_>>();
+        if options.no_input && !options.overwrite && !existing_files.is_empty() {
+            println!(
+                "{}",
+                "🚨 Files already exist, use --overwrite to replace them:".red()
+            );
+            for file in existing_files {
+                println!("   {}", TemplatePlan::display_path(&file.path, cwd));
             }
+            std::process::exit(1);
+        }
 
-            if is_append_mode {
-                if !path.exists() {
-                    fs::write(&path, new_content).unwrap();
-                } else {
-                    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
-                    file.write_all(new_content.as_bytes()).unwrap();
-                }
-
-                continue;
-            } else if path.exists() {
+        let mut confirmed_plan = TemplatePlan::new();
+        for file in plan.files {
+            if file.action == TemplatePlanAction::Overwrite && !options.overwrite {
                 let can_overwrite = CliCommands::confirm(&format!(
                     "File {} already exists. Do you want to overwrite?",
-                    path.to_str().unwrap()
+                    file.path.to_str().unwrap()
                 ));
-                if can_overwrite {
-                    fs::write(&path, new_content).unwrap();
+                if !can_overwrite {
+                    continue;
                 }
-            } else {
-                fs::write(&path, new_content).unwrap();
             }
+            confirmed_plan.files.push(file);
+        }
+        confirmed_plan
+    }
+
+    /**
+     * Write the plan and record it in generation manifest, so it can be undone.
+     * Empty plan is not recorded, otherwise `gen undo` would revert nothing.
+     */
+    pub fn apply_plan(
+        cwd: &Path,
+        template_name: &str,
+        values_for_keys: &HashMap<String, String>,
+        plan: &TemplatePlan,
+    ) -> Result<Option<TemplateManifest>, String> {
+        if plan.files.is_empty() {
+            return Ok(None);
+        }
+        let mut manifest = TemplateManifest::from_plan(cwd, template_name, values_for_keys, plan);
+        if let Err(error) = manifest.save_backups(cwd) {
+            manifest.remove();
+            return Err(error);
+        }
+
+        let created_directories = plan.apply().map_err(|error| {
+            manifest.remove();
+            format!(
+                "{}. Nothing was generated, all changes were rolled back.",
+                error
+            )
+        })?;
+        manifest.directories = created_directories
+            .iter()
+            .map(|directory| TemplatePlan::display_path(directory, cwd))
+            .collect();
+        if let Err(error) = manifest.save() {
+            println!("{}", format!("🚨 {}", error).yellow());
+        }
+        Ok(Some(manifest))
+    }
+
+    /**
+     * Positional name is the value of unnamed `__var__`, so it would be lost in template
+     * which uses named variables only.
+     */
+    fn check_name(
+        template_folder: &TemplateFolder,
+        result: &SearchResult,
+        options: &TemplateUseOptions,
+    ) {
+        let Some(name) = &options.name else {
+            return;
+        };
+        let key = format!("{}_", TemplateVariable::Var);
+        if result.variables.contains_key(&key) {
+            return;
+        }
+        println!(
+            "{} {} {}",
+            "🚨 Template".red(),
+            template_folder.name.bold(),
+            format!(
+                "has no unnamed {}, name {} cannot be used.",
+                TemplateVariable::Var,
+                name
+            )
+            .red()
+        );
+        // named `__var__` is the most likely replacement of the name
+        let variable = result
+            .variables
+            .values()
+            .filter(|variable| variable.template_variable.is_value())
+            .min_by_key(|variable| !variable.template_variable.is_var());
+        if let Some(variable) = variable {
+            let hint = format!("--set {}={}", TemplateAnswers::key_hint(variable), name);
+            println!("Set its variables instead, e.g. {}", hint.cyan());
+        }
+        std::process::exit(1);
+    }
+
+    pub fn use_it(
+        local_config: &Config,
+        global_config: &Config,
+        template_folder: &TemplateFolder,
+        options: &TemplateUseOptions,
+    ) {
+        let result = SearchFolder::search(&template_folder.path);
+        println!();
+        println!("Using template: {}", template_folder.name.green().bold());
+        if !result.template_config.description.is_empty() {
+            println!();
+            println!("Description: {}", result.template_config.description);
+        }
+        println!();
+        TemplateUse::check_name(template_folder, &result, options);
+        let values_for_keys = TemplateUse::resolve_values(template_folder, &result, options);
+        if let Err(missing) = values_for_keys {
+            TemplateUse::print_missing_values(&missing);
+            std::process::exit(1);
+        }
+        let values_for_keys = values_for_keys.unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        let case_resolver = TemplateCaseResolver::new(
+            &result.template_config,
+            Some(&local_config.config),
+            &global_config.config,
+        );
+        let plan = TemplateUse::create_plan(&case_resolver, &result, &values_for_keys, &cwd);
+        if let Err(error) = plan {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        let mut plan = plan.unwrap();
+        if !options.dry_run {
+            plan = TemplateUse::confirm_overwrites(plan, options, &cwd);
+        }
+        if let Err(error) = plan.register_files() {
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+        for path in plan.skip_present_content() {
+            println!(
+                "{} {}",
+                "Skipping file, content is already there:".yellow(),
+                TemplatePlan::display_path(&path, &cwd)
+            );
+        }
+
+        if options.dry_run {
+            plan.print_dry_run(&cwd);
+            return;
+        }
+
+        if plan.files.is_empty() {
+            println!();
+            println!(
+                "{}",
+                "🤷 Nothing to generate, all files are skipped.".yellow()
+            );
+            println!();
+            return;
+        }
+        if let Err(error) =
+            TemplateUse::apply_plan(&cwd, &template_folder.name, &values_for_keys, &plan)
+        {
+            println!();
+            println!("{}", format!("🚨 {}", error).red());
+            std::process::exit(1);
+        }
+
+        for file in plan.files.iter() {
+            println!(
+                "{} file: {}",
+                match file.action {
+                    TemplatePlanAction::Create => "Created",
+                    TemplatePlanAction::Overwrite => "Overwritten",
+                    TemplatePlanAction::Append => "Appended",
+                    TemplatePlanAction::Merge => "Merged",
+                    TemplatePlanAction::Register => "Registered in",
+                },
+                file.path.to_str().unwrap().green()
+            );
         }
 
         println!();
@@ -1,16 +1,56 @@
-use convert_case::{Case, Casing};
+use convert_case::{Case, Casing, Converter, Pattern};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Default)]
 pub enum CaseType {
-    SnakeCase,  // snake_case
-    KebabCase,  // kebab-case
-    CamelCase,  // camelCase
-    PascalCase, // PascalCase
-    Unknown,    // none
+    SnakeCase,          // snake_case
+    KebabCase,          // kebab-case
+    CamelCase,          // camelCase
+    PascalCase,         // PascalCase
+    ScreamingSnakeCase, // SCREAMING_SNAKE_CASE
+    TitleCase,          // Title Case
+    DotCase,            // dot.case
+    PathCase,           // path/case
+    FlatCase,           // flatcase
+    TrainCase,          // Train-Case
+    LowerCase,          // lower case
+    UpperCase,          // UPPER CASE
+    #[default]
+    Unknown, // none
 }
 
 impl CaseType {
+    /**
+     * All selectable case types, in order they are offered in prompts.
+     */
+    pub fn all() -> Vec<CaseType> {
+        vec![
+            CaseType::SnakeCase,
+            CaseType::KebabCase,
+            CaseType::CamelCase,
+            CaseType::PascalCase,
+            CaseType::ScreamingSnakeCase,
+            CaseType::TitleCase,
+            CaseType::DotCase,
+            CaseType::PathCase,
+            CaseType::FlatCase,
+            CaseType::TrainCase,
+            CaseType::LowerCase,
+            CaseType::UpperCase,
+        ]
+    }
+
+    /**
+     * Get case type by its display name, e.g. `SCREAMING_SNAKE_CASE` or `dot.case`,
+     * other values are detected by their shape.
+     */
+    pub fn from_str_name(value: &str) -> CaseType {
+        CaseType::all()
+            .into_iter()
+            .find(|case_type| case_type.to_str_name() == value)
+            .unwrap_or_else(|| CaseType::from_str(value))
+    }
+
     pub fn from_str(value: &str) -> CaseType {
         let snake_case = value.is_case(Case::Snake);
         let kebab_case = value.is_case(Case::Kebab);
@@ -33,16 +73,71 @@ impl CaseType {
         CaseType::Unknown
     }
 
-    pub fn from_string_to_case(&self, value: &str) -> String {
+    /**
+     * Get case type by suffix used in template variables, e.g. `kebab` from `__var__kebab__`.
+     */
+    pub fn from_suffix(value: &str) -> CaseType {
+        match value.strip_suffix("__").unwrap_or(value) {
+            "kebab" => CaseType::KebabCase,
+            "snake" => CaseType::SnakeCase,
+            "camel" => CaseType::CamelCase,
+            "pascal" => CaseType::PascalCase,
+            "screaming" => CaseType::ScreamingSnakeCase,
+            "title" => CaseType::TitleCase,
+            "dot" => CaseType::DotCase,
+            "path" => CaseType::PathCase,
+            "flat" => CaseType::FlatCase,
+            "train" => CaseType::TrainCase,
+            "lower" => CaseType::LowerCase,
+            "upper" => CaseType::UpperCase,
+            _ => CaseType::Unknown,
+        }
+    }
+
+    /**
+     * Parse case type from command line, accepts suffix (`kebab`), display name (`kebab-case`)
+     * or name used in `_.json` (`KebabCase`).
+     */
+    pub fn from_argument(value: &str) -> CaseType {
+        let case_type = CaseType::from_suffix(&value.to_lowercase());
+        if case_type.is_not_unknown() {
+            return case_type;
+        }
+        let case_type = CaseType::all()
+            .into_iter()
+            .find(|case_type| case_type.to_str_name() == value);
+        if let Some(case_type) = case_type {
+            return case_type;
+        }
+        serde_json::from_value(serde_json::Value::String(value.to_string()))
+            .unwrap_or(CaseType::Unknown)
+    }
+
+    pub fn to_case_string(&self, value: &str) -> String {
         match self {
             CaseType::SnakeCase => value.to_case(Case::Snake),
             CaseType::KebabCase => value.to_case(Case::Kebab),
             CaseType::CamelCase => value.to_case(Case::Camel),
             CaseType::PascalCase => value.to_case(Case::Pascal),
+            CaseType::ScreamingSnakeCase => value.to_case(Case::ScreamingSnake),
+            CaseType::TitleCase => value.to_case(Case::Title),
+            CaseType::DotCase => CaseType::to_delimited(value, "."),
+            CaseType::PathCase => CaseType::to_delimited(value, "/"),
+            CaseType::FlatCase => value.to_case(Case::Flat),
+            CaseType::TrainCase => value.to_case(Case::Train),
+            CaseType::LowerCase => value.to_case(Case::Lower),
+            CaseType::UpperCase => value.to_case(Case::Upper),
             CaseType::Unknown => value.to_string(),
         }
     }
 
+    fn to_delimited(value: &str, delimiter: &str) -> String {
+        Converter::new()
+            .set_pattern(Pattern::Lowercase)
+            .set_delim(delimiter)
+            .convert(value)
+    }
+
     pub fn is_not_unknown(&self) -> bool {
         self != &CaseType::Unknown
     }
@@ -53,6 +148,14 @@ impl CaseType {
             CaseType::KebabCase => "kebab-case",
             CaseType::CamelCase => "camelCase",
             CaseType::PascalCase => "PascalCase",
+            CaseType::ScreamingSnakeCase => "SCREAMING_SNAKE_CASE",
+            CaseType::TitleCase => "Title Case",
+            CaseType::DotCase => "dot.case",
+            CaseType::PathCase => "path/case",
+            CaseType::FlatCase => "flatcase",
+            CaseType::TrainCase => "Train-Case",
+            CaseType::LowerCase => "lower case",
+            CaseType::UpperCase => "UPPER CASE",
             CaseType::Unknown => "unknown",
         }
     }
@@ -95,4 +198,47 @@ mod tests {
         let result = CaseType::from_str("helloWorld_");
         assert_eq!(result, CaseType::Unknown);
     }
+
+    #[test]
+    fn should_convert_to_extended_cases() {
+        let value = "my button name";
+        assert_eq!(
+            CaseType::ScreamingSnakeCase.to_case_string(value),
+            "MY_BUTTON_NAME"
+        );
+        assert_eq!(CaseType::TitleCase.to_case_string(value), "My Button Name");
+        assert_eq!(
+            CaseType::DotCase.to_case_string("myButtonName"),
+            "my.button.name"
+        );
+        assert_eq!(
+            CaseType::PathCase.to_case_string("MyButton-name"),
+            "my/button/name"
+        );
+        assert_eq!(CaseType::FlatCase.to_case_string(value), "mybuttonname");
+        assert_eq!(CaseType::TrainCase.to_case_string(value), "My-Button-Name");
+        assert_eq!(CaseType::LowerCase.to_case_string("MyButton"), "my button");
+        assert_eq!(CaseType::UpperCase.to_case_string("my_button"), "MY BUTTON");
+    }
+
+    #[test]
+    fn should_get_case_type_by_name() {
+        for case_type in CaseType::all() {
+            assert_eq!(CaseType::from_str_name(case_type.to_str_name()), case_type);
+        }
+        assert_eq!(CaseType::from_str_name("my_name"), CaseType::SnakeCase);
+        assert_eq!(CaseType::from_str_name("nope-"), CaseType::Unknown);
+    }
+
+    #[test]
+    fn should_parse_case_type_argument() {
+        assert_eq!(CaseType::from_argument("kebab"), CaseType::KebabCase);
+        assert_eq!(
+            CaseType::from_argument("Screaming"),
+            CaseType::ScreamingSnakeCase
+        );
+        assert_eq!(CaseType::from_argument("dot.case"), CaseType::DotCase);
+        assert_eq!(CaseType::from_argument("TitleCase"), CaseType::TitleCase);
+        assert_eq!(CaseType::from_argument("nope"), CaseType::Unknown);
+    }
 }
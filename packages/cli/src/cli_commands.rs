@@ -158,24 +158,16 @@ impl CliCommands {
     }
 
     pub fn case_type(case_type: Option<CaseType>, text: &str) -> Result<CaseType, String> {
-        let items = vec![
-            CaseType::SnakeCase.to_str_name(),
-            CaseType::KebabCase.to_str_name(),
-            CaseType::CamelCase.to_str_name(),
-            CaseType::PascalCase.to_str_name(),
-        ];
-
-        let default = if let Some(case_type) = case_type {
-            match case_type {
-                CaseType::SnakeCase => 0,
-                CaseType::KebabCase => 1,
-                CaseType::CamelCase => 2,
-                CaseType::PascalCase => 3,
-                _ => 0,
-            }
-        } else {
-            0
-        };
+        let case_types = CaseType::all();
+        let items = case_types
+            .iter()
+            .map(|case_type| case_type.to_str_name())
+            .collect::<Vec<_>>();
+
+        let default = case_types
+            .iter()
+            .position(|item| Some(item) == case_type.as_ref())
+            .unwrap_or(0);
         let result = Select::new(&format!("{}:", text), items)
             .with_starting_cursor(default)
             .prompt();
@@ -183,7 +175,7 @@ impl CliCommands {
             return Err("Case type cannot be empty".to_string());
         }
         let result = result.unwrap();
-        let case_type = CaseType::from_str(result);
+        let case_type = CaseType::from_str_name(result);
         Ok(case_type)
     }
 
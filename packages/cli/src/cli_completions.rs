@@ -0,0 +1,158 @@
+use crate::{
+    config::Config,
+    constants::{COMPLETE_VAR, CONFIG_FILE},
+    template::TemplateFolder,
+    template_answers::TemplateAnswers,
+    template_variable::TemplateVariableInfo,
+};
+use clap_complete::{engine::CompletionCandidate, env::Shells};
+use std::{collections::HashMap, env, ffi::OsStr, fs, io};
+
+/**
+ * Shell completions. Scripts printed by `gen completions <shell>` call back into gen
+ * (`COMPLETE=<shell> gen -- ...`), so template names and select options are always
+ * read from current `.gen/` & `~/.gen/` folders.
+ */
+pub struct CliCompletions;
+
+impl CliCompletions {
+    pub fn print_script(shell: &str) -> Result<(), String> {
+        let shells = Shells::builtins();
+        let completer = shells
+            .completer(shell)
+            .ok_or_else(|| format!("Unsupported shell '{}'", shell))?;
+        completer
+            .write_registration(COMPLETE_VAR, "gen", "gen", "gen", &mut io::stdout())
+            .map_err(|error| format!("Cannot write completions: {}", error))
+    }
+
+    /**
+     * Local templates first, global templates with same name are hidden by local ones.
+     */
+    fn template_folders() -> Vec<TemplateFolder> {
+        let mut template_folders = Vec::new();
+        for directory in [Config::local_path(), Config::global_path()] {
+            for template_folder in Config::load_template_folders(&directory).template_folders {
+                let is_exist = template_folders
+                    .iter()
+                    .any(|item: &TemplateFolder| item.name == template_folder.name);
+                if !is_exist {
+                    template_folders.push(template_folder);
+                }
+            }
+        }
+        template_folders
+    }
+
+    /**
+     * Template `_.json` is read loosely, broken config should not break completion.
+     */
+    fn read_template_config(template_folder: &TemplateFolder) -> serde_json::Value {
+        fs::read_to_string(template_folder.path.join(CONFIG_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn templates() -> Vec<CompletionCandidate> {
+        CliCompletions::template_folders()
+            .iter()
+            .map(|template_folder| {
+                let config = CliCompletions::read_template_config(template_folder);
+                let description = config["description"]
+                    .as_str()
+                    .filter(|description| !description.is_empty())
+                    .map(|description| description.to_string().into());
+                CompletionCandidate::new(&template_folder.name).help(description)
+            })
+            .collect()
+    }
+
+    /**
+     * `__select__` values for `--set`. When template name is already typed, only its
+     * options are offered, otherwise options of all templates.
+     */
+    pub fn set_values(current: &OsStr) -> Vec<CompletionCandidate> {
+        let current = current.to_string_lossy();
+        let arguments = env::args().collect::<Vec<_>>();
+        let template_folders = CliCompletions::template_folders();
+        let typed_template_folders = template_folders
+            .iter()
+            .filter(|template_folder| arguments.contains(&template_folder.name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let template_folders = if typed_template_folders.is_empty() {
+            template_folders
+        } else {
+            typed_template_folders
+        };
+
+        let mut select_options = Vec::new();
+        for template_folder in template_folders.iter() {
+            let config = CliCompletions::read_template_config(template_folder);
+            let options: HashMap<String, Vec<String>> =
+                serde_json::from_value(config["select_options"].to_owned()).unwrap_or_default();
+            for (key, values) in options {
+                select_options.push((key, values));
+            }
+        }
+        CliCompletions::get_set_values(&select_options, &current)
+    }
+
+    fn get_set_values(
+        select_options: &[(String, Vec<String>)],
+        current: &str,
+    ) -> Vec<CompletionCandidate> {
+        let mut set_values = Vec::new();
+        for (key, values) in select_options {
+            let variable = TemplateVariableInfo::from_str(key);
+            let Some(variable) = variable.filter(|item| item.template_variable.is_select()) else {
+                continue;
+            };
+            let key_hint = TemplateAnswers::key_hint(&variable);
+            for value in values {
+                let set_value = format!("{}={}", key_hint, value);
+                if set_value.starts_with(current) && !set_values.contains(&set_value) {
+                    set_values.push(set_value);
+                }
+            }
+        }
+        set_values.sort();
+        set_values
+            .into_iter()
+            .map(CompletionCandidate::new)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CliCompletions;
+
+    #[test]
+    fn should_complete_set_values() {
+        let select_options = vec![
+            (
+                "__select__kind__".to_string(),
+                vec!["basic".to_string(), "compound".to_string()],
+            ),
+            ("__select__".to_string(), vec!["react".to_string()]),
+            ("__var__name__".to_string(), vec!["ignored".to_string()]),
+        ];
+        let values = CliCompletions::get_set_values(&select_options, "")
+            .iter()
+            .map(|item| item.get_value().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                "__select__=react",
+                "__select__kind=basic",
+                "__select__kind=compound"
+            ]
+        );
+
+        let values = CliCompletions::get_set_values(&select_options, "__select__kind=c");
+        assert_eq!(values.len(), 1);
+    }
+}
@@ -1,40 +1,43 @@
 use crate::{
-    actions::{TemplateAction, TemplateFetch, TemplateUse},
+    actions::{
+        TemplateAction, TemplateFetch, TemplateInit, TemplatePublish, TemplateUndo, TemplateUpdate,
+        TemplateUse, TemplateUseOptions,
+    },
     cli_commands::CliCommands,
-    commands::Commands,
+    cli_completions::CliCompletions,
+    commands::{Cli, Commands, UseArgs},
     config::{Config, ConfigFile},
-    constants::{CLI_VERSION, TEMPLATE_ROOT_FOLDER},
+    constants::{CLI_VERSION, COMPLETE_VAR},
     template::{TemplateConfig, TemplateFolder},
+    template_cache::TemplateCache,
 };
+use clap::CommandFactory;
+use clap_complete::CompleteEnv;
 use colored::Colorize;
 use loading::Loading;
-use std::{collections::HashSet, env, fs, path::Path};
+use std::{env, fs};
 /**
  * CLI TOOL - name gen - but gen is name of the tool, so it will be in bash profile.
- * Template arguments:
- * - empty - will show list of templates and after select, it will go to template name selection
- * - [template name] - will go to template name selection
- * - [template name] [custom name] - will go to template file select
- * Flags for template arguments:
- * --edit -e - will go to template editor
- * --delete -d - will delete template
- * --publish -p - will publish template to github
- * --unpublish -u - will unpublish template from github
- *
- *
- * Template new argument:
- * - new - will go new to template editor
- *
- * Flags for template new argument:
- * --cloud -c - will create template in cloud
- * --local -l - will create template locally
- * without argument it will save it to global folder - ~/.somewhere/templates
+ * Commands are parsed by clap, see `Commands` for the list, each command has its own `--help`.
+ * - empty - will show guide with list of commands
+ * - [template name] [custom name] - shortcut for use, custom name fills unnamed __var__
+ * - init - will create .gen/_.json config, or ~/.gen/_.json with --global flag
+ * - new [template name] - will go new to template editor
+ * - edit / delete / explain [template name] - will select template from list when name is missing
+ * - use [template name] [custom name] [--set key=value] [--answers file] [--no-input] [--dry-run] [--offline]
+ *   template can be remote, e.g. git+file:///repo#component, it is used from cache without fetching
+ * - destroy [template name] - will remove previously generated template instance, same flags as use
+ * - undo - will revert last use of template
+ * - fetch <source[#subdir]> [--ref ref] [--path subdir] [--as name] - will fetch templates from git repository, directory or archive
+ * - publish [template name] --to <repository> [-m message] - will push template to .gen folder of git repository
+ * - unpublish [template name] --to <repository> [-m message] - will remove template from git repository
+ * - update [template name] [--force] [--ref ref] - will fetch templates again from source recorded in gen.lock
+ * - completions <shell> - will print shell completion script
  *
  * Global flags:
+ * --global -g - will use global scope (~/.gen)
  * --help -h - will show help
- * --version -v - will show version
- * --list -l - will show list of templates - but same as empty template argument
- * --search -s - will search for templates on github
+ * --version -V - will show version
  */
 
 #[derive(Debug)]
@@ -42,161 +45,213 @@ pub struct CliParser {}
 
 impl CliParser {
     pub fn parse() {
-        let vec_arguments: Vec<String> = env::args().skip(1).collect();
-        let vec_arguments_cloned = Commands::return_unknown_arguments(&vec_arguments);
-        let second_argument = &vec_arguments_cloned.first();
-        let arguments: HashSet<String> = HashSet::from_iter(vec_arguments);
-        let is_global = Commands::Global.is_command_from_set(&arguments);
-        let cwd = env::current_dir().unwrap();
-
-        let local_config_dir_path = Path::new(&cwd).join(TEMPLATE_ROOT_FOLDER).to_owned();
-        let global_config_dir_path = Path::new(&env::var("HOME").unwrap())
-            .join(TEMPLATE_ROOT_FOLDER)
-            .to_owned();
+        CompleteEnv::with_factory(Cli::command)
+            .var(COMPLETE_VAR)
+            .complete();
+        let cli = Cli::try_parse_args(env::args_os()).unwrap_or_else(|error| error.exit());
+        let local_config_dir_path = Config::local_path();
+        let global_config_dir_path = Config::global_path();
 
         let mut local_config = Config::load_template_folders(&local_config_dir_path);
         let mut global_config = Config::load_template_folders(&global_config_dir_path);
 
-        local_config.config = ConfigFile::load_config(&local_config_dir_path, !is_global);
-        global_config.config = ConfigFile::load_config(&global_config_dir_path, is_global);
+        local_config.config = ConfigFile::load_config(&local_config_dir_path);
+        global_config.config = ConfigFile::load_config(&global_config_dir_path);
 
-        let config = if is_global {
+        let config = if cli.global {
             &global_config
         } else {
             local_config.config.merge(&global_config.config);
             &local_config
         };
 
-        if config.config.open_editor_command.is_none() {
-            global_config.config.open_editor_command = TemplateAction::get_template_command_args();
-            local_config.config.open_editor_command =
-                global_config.config.open_editor_command.clone();
-            global_config.config.save_config(&global_config_dir_path);
-        }
-
-        let config = if is_global {
-            &global_config
-        } else {
-            local_config.config.merge(&global_config.config);
-            &local_config
+        let command = match cli.command {
+            Some(command) => command,
+            None => {
+                Commands::print_guide();
+                return;
+            }
         };
 
-        if Commands::Help.is_command_from_set(&arguments) || arguments.is_empty() {
-            Commands::print_help();
-            return;
-        }
-
-        if Commands::Version.is_command_from_set(&arguments) {
-            println!("Version: {}", CLI_VERSION.bold());
-            return;
-        }
-
-        if Commands::New.is_command_from_set(&arguments) {
-            if let Some(template_name) = second_argument {
-                let template_folder = TemplateFolder::new(config, template_name);
+        match command {
+            Commands::Version => {
+                println!("Version: {}", CLI_VERSION.bold());
+            }
+            Commands::Init(init_args) => {
+                TemplateInit::init(&config.path, &init_args.to_options());
+            }
+            Commands::New { template } => {
+                if let Some(template_name) = template {
+                    let template_folder = TemplateFolder::new(config, &template_name);
+                    CliParser::edit_create_selected_template(config, &template_folder);
+                    return;
+                }
+                TemplateAction::new_template(config);
+            }
+            Commands::Edit { template } => {
+                let template_folder = CliParser::get_template_folder(config, template, false);
                 CliParser::edit_create_selected_template(config, &template_folder);
-                return;
             }
-            TemplateAction::new_template(config);
-            return;
-        }
-
-        if Commands::Edit.is_command_from_set(&arguments) {
-            let template_folder = if let Some(template_name) = second_argument {
-                Ok(TemplateFolder::new_empty(config, template_name))
-            } else {
-                CliParser::get_list(config)
-            };
-            if template_folder.is_err() {
-                return;
+            Commands::Publish(publish_args) => {
+                let template_folder =
+                    CliParser::get_template_folder(config, publish_args.template.clone(), false);
+                TemplatePublish::publish(&template_folder, &publish_args.to_options());
             }
-            let template_folder = template_folder.unwrap();
-
-            if !CliParser::is_exist_and_prompt(config, &template_folder) {
-                return;
+            Commands::Unpublish(publish_args) => {
+                // template does not have to exist locally to be removed from repository
+                let template_name = match publish_args.template.clone() {
+                    Some(template_name) => template_name,
+                    None => {
+                        CliParser::get_list(config)
+                            .unwrap_or_else(|_| std::process::exit(1))
+                            .name
+                    }
+                };
+                TemplatePublish::unpublish(&template_name, &publish_args.to_options());
+            }
+            Commands::Update {
+                template,
+                force,
+                git_ref,
+            } => {
+                TemplateUpdate::update(config, template.as_deref(), git_ref.as_deref(), force);
+            }
+            Commands::Refresh => {
+                TemplateAction::refresh_templates(config);
+            }
+            Commands::Variables => {
+                TemplateAction::list_of_all_variables(config);
+            }
+            Commands::Fetch(fetch_args) => {
+                TemplateFetch::fetch(config, &fetch_args.source, &fetch_args.to_options());
+            }
+            Commands::Use(use_args) => {
+                let use_options = CliParser::get_use_options(&use_args);
+                let template_folder = CliParser::get_use_template_folder(config, &use_args);
+                TemplateUse::use_it(
+                    &local_config,
+                    &global_config,
+                    &template_folder,
+                    &use_options,
+                );
+            }
+            Commands::Explain { template } => {
+                let template_folder = CliParser::get_template_folder(config, template, false);
+                TemplateAction::explain_template(&local_config, &global_config, &template_folder);
+            }
+            Commands::Completions { shell } => {
+                if let Err(error) = CliCompletions::print_script(&shell) {
+                    println!("{}", format!("🚨 {}", error).red());
+                    std::process::exit(1);
+                }
+            }
+            Commands::Undo => {
+                TemplateUndo::undo_last();
+            }
+            Commands::Destroy(use_args) => {
+                let use_options = CliParser::get_use_options(&use_args);
+                let template_folder = CliParser::get_use_template_folder(config, &use_args);
+                TemplateUndo::destroy(
+                    &local_config,
+                    &global_config,
+                    &template_folder,
+                    &use_options,
+                );
+            }
+            Commands::Delete { template } => {
+                let template_folder = CliParser::get_template_folder(config, template, false);
+                CliParser::delete_template(&template_folder);
             }
-
-            CliParser::edit_create_selected_template(config, &template_folder);
-            return;
-        }
-
-        if Commands::Refresh.is_command_from_set(&arguments) {
-            TemplateAction::refresh_templates(config);
-            return;
-        }
-        if Commands::VariablesList.is_command_from_set(&arguments) {
-            TemplateAction::list_of_all_variables(config);
-            return;
         }
+    }
 
-        if Commands::Fetch.is_command_from_set(&arguments) {
-            if let Some(url) = second_argument {
-                TemplateFetch::fetch_github(config, url);
-                return;
-            } else {
-                println!("{}", "🚨 Missing url, github url or path argument.".red());
+    fn get_use_options(use_args: &UseArgs) -> TemplateUseOptions {
+        match use_args.to_options() {
+            Ok(use_options) => use_options,
+            Err(error) => {
+                println!("{}", format!("🚨 {}", error).red());
+                std::process::exit(1);
             }
-
-            return;
         }
+    }
 
-        if Commands::Use.is_command_from_set(&arguments) {
-            let template_folder = if let Some(template_name) = second_argument {
-                Ok(TemplateFolder::new_empty(config, template_name))
-            } else {
-                CliParser::get_list(config)
-            };
-            if template_folder.is_err() {
-                return;
+    /**
+     * Remote templates are used from cache without fetching them, see `TemplateCache`.
+     */
+    fn get_use_template_folder(config: &Config, use_args: &UseArgs) -> TemplateFolder {
+        match use_args.template.as_deref() {
+            Some(template) if TemplateCache::is_remote(template) => {
+                let loading = Loading::default();
+                loading.text(format!("Fetching {}...", template).blue());
+                let result = TemplateCache::resolve(template, use_args.offline);
+                loading.end();
+                result.unwrap_or_else(|error| {
+                    println!("{}", format!("🚨 {}", error).red());
+                    std::process::exit(1);
+                })
             }
-            let template_folder = template_folder.unwrap();
-            if !CliParser::is_exist_and_prompt(config, &template_folder) {
-                return;
+            _ => {
+                CliParser::get_template_folder(config, use_args.template.clone(), use_args.no_input)
             }
+        }
+    }
 
-            TemplateUse::use_it(&global_config, config, &template_folder);
+    /**
+     * Existing template by name, or selected from list when name is missing.
+     * Exits with error when template does not exist or nothing was selected.
+     */
+    fn get_template_folder(
+        config: &Config,
+        template_name: Option<String>,
+        no_input: bool,
+    ) -> TemplateFolder {
+        let template_folder = if let Some(template_name) = template_name {
+            Ok(TemplateFolder::new_empty(config, &template_name))
+        } else if no_input {
+            println!("{}", "🚨 Missing template name argument.".red());
+            std::process::exit(1);
+        } else {
+            CliParser::get_list(config)
+        };
+        if template_folder.is_err() {
+            std::process::exit(1);
+        }
+        let template_folder = template_folder.unwrap();
+        if !CliParser::is_exist_and_prompt(config, &template_folder) {
+            std::process::exit(1);
+        }
+        template_folder
+    }
 
+    fn delete_template(template_folder: &TemplateFolder) {
+        let text = format!(
+            "{} {}",
+            "🚨 Are you sure you want to delete".red(),
+            template_folder.name.bold().green()
+        );
+        let is_ok = CliCommands::confirm(&text);
+        if !is_ok {
             return;
         }
-
-        if Commands::Delete.is_command_from_set(&arguments) {
-            let template_folder = if let Some(template_name) = second_argument {
-                Ok(TemplateFolder::new_empty(config, template_name))
-            } else {
-                CliParser::get_list(config)
-            };
-            if template_folder.is_err() {
-                return;
-            }
-            let template_folder = template_folder.unwrap();
-            let text = format!(
-                "{} {}",
-                "🚨 Are you sure you want to delete".red(),
-                template_folder.name.bold().green()
+        let loading = Loading::default();
+        loading.text("Removing template...".blue());
+        let result = fs::remove_dir_all(&template_folder.path);
+        if result.is_err() {
+            println!();
+            println!(
+                "{}",
+                format!(
+                    "🚨 Error while removing template: {}",
+                    result.err().unwrap()
+                )
+                .red()
             );
-            let is_ok = CliCommands::confirm(&text);
-            if is_ok {
-                let loading = Loading::default();
-                loading.text("Removing template...".blue());
-                let result = fs::remove_dir_all(&template_folder.path);
-                if result.is_err() {
-                    println!();
-                    println!(
-                        "{}",
-                        format!(
-                            "🚨 Error while removing template: {}",
-                            result.err().unwrap()
-                        )
-                        .red()
-                    );
-                    loading.end();
-                    return;
-                }
-                println!();
-                loading.success("Template removed.".green());
-                loading.end();
-            }
+            loading.end();
+            std::process::exit(1);
         }
+        println!();
+        loading.success("Template removed.".green());
+        loading.end();
     }
 
     fn is_exist_and_prompt(config: &Config, template_folder: &TemplateFolder) -> bool {
@@ -215,12 +270,12 @@ impl CliParser {
         );
         println!();
         let similar_word_match = CliParser::get_similar_word_match(config, &template_folder.name);
-        if similar_word_match.is_some() {
+        if let Some(similar_word_match) = similar_word_match {
             println!(
                 "{}",
                 format!(
                     "🤔 Did you mean {}?",
-                    similar_word_match.unwrap().name.bold().green()
+                    similar_word_match.name.bold().green()
                 )
                 .yellow()
             );
@@ -284,7 +339,7 @@ impl CliParser {
             .map(|item| (item, CliParser::get_fuzzy_score(&item.name, template_name)))
             .filter(|item| item.1 > 0)
             .collect::<Vec<_>>();
-        sorted_template_folders.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted_template_folders.sort_by_key(|item| std::cmp::Reverse(item.1));
         let template_folder = sorted_template_folders.first();
         template_folder?;
         let template_folder = template_folder.unwrap();
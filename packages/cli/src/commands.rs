@@ -1,102 +1,319 @@
-use std::collections::HashSet;
-
+use crate::{
+    actions::{
+        TemplateAction, TemplateFetchOptions, TemplateInit, TemplateInitOptions,
+        TemplatePublishOptions, TemplateUseOptions,
+    },
+    case_util::CaseType,
+    cli_completions::CliCompletions,
+    constants::{CLI_VERSION, COMPLETION_SHELLS, TEMPLATE_DOCS_URL, TEMPLATE_VARIABLE},
+    template_answers::TemplateAnswers,
+};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::engine::{ArgValueCandidates, ArgValueCompleter, SubcommandCandidates};
 use colored::Colorize;
+use std::ffi::OsString;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "gen",
+    version = CLI_VERSION,
+    about = "Easy to use template generator for any code any project.",
+    after_help = format!(
+        "Shortcut: gen <TEMPLATE> [NAME] [OPTIONS] is same as gen use <TEMPLATE> [NAME] [OPTIONS]\n\nFor more info visit: {}",
+        TEMPLATE_DOCS_URL
+    ),
+    add = SubcommandCandidates::new(CliCompletions::templates)
+)]
+pub struct Cli {
+    /// Use global scope (~/.gen) instead of project .gen folder
+    #[arg(short, long, global = true)]
+    pub global: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+/**
+ * Parsed command line, template shortcut is already converted to `use` command.
+ */
+#[derive(Debug)]
+pub struct CliArgs {
+    pub global: bool,
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    #[command(flatten)]
+    Command(Commands),
+    /// Template name with `use` arguments, `gen component Button`
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
 
-use crate::{actions::TemplateAction, constants::TEMPLATE_DOCS_URL};
+#[derive(Debug, Subcommand)]
 pub enum Commands {
-    New,
-    Use,
-    Edit,
-    Delete,
-    Publish,
-    Unpublish,
-    Global,
+    /// Create .gen/_.json config (~/.gen/_.json with --global). Prompts for case types & editor when no flags are set
+    Init(InitArgs),
+    /// Create new template. Local template folder is generated to current (cwd) path
+    #[command(visible_alias = "n")]
+    New {
+        /// Template name, prompted when missing
+        template: Option<String>,
+    },
+    /// Edit template
+    #[command(visible_alias = "e")]
+    Edit {
+        /// Template name, selected from list when missing
+        #[arg(add = ArgValueCandidates::new(CliCompletions::templates))]
+        template: Option<String>,
+    },
+    /// Delete template
+    #[command(visible_alias = "d")]
+    Delete {
+        /// Template name, selected from list when missing
+        #[arg(add = ArgValueCandidates::new(CliCompletions::templates))]
+        template: Option<String>,
+    },
+    /// Use template
+    #[command(visible_alias = "u")]
+    Use(UseArgs),
+    /// Fetch templates from git repository, local directory or archive
+    #[command(visible_alias = "f")]
+    Fetch(FetchArgs),
+    /// Copy template to .gen folder of git repository, commit and push it
+    #[command(visible_alias = "p")]
+    Publish(PublishArgs),
+    /// Remove template from .gen folder of git repository, commit and push it
+    Unpublish(PublishArgs),
+    /// Fetch templates again from source recorded in .gen/gen.lock and show what changed
+    Update {
+        /// Template name, all fetched templates are updated when missing
+        #[arg(add = ArgValueCandidates::new(CliCompletions::templates))]
+        template: Option<String>,
+        /// Overwrite templates which were edited since they were fetched
+        #[arg(short, long)]
+        force: bool,
+        /// Branch, tag or commit sha to update the template to, recorded in gen.lock.
+        /// Without it the recorded ref is fetched again, so branches move but pinned tags and commit shas stay
+        #[arg(long = "ref", value_name = "REF", requires = "template")]
+        git_ref: Option<String>,
+    },
+    /// Refresh select options of all templates
+    #[command(visible_alias = "r")]
     Refresh,
-    Help,
+    /// List of all templates variables
+    #[command(visible_alias = "vv")]
+    Variables,
+    /// Show which level decided case of each variable: inline suffix > variable in template _.json > template case_type > project .gen/_.json > global ~/.gen/_.json
+    #[command(visible_alias = "x")]
+    Explain {
+        /// Template name, selected from list when missing
+        #[arg(add = ArgValueCandidates::new(CliCompletions::templates))]
+        template: Option<String>,
+    },
+    /// Undo last use. Created files are deleted, appended parts stripped and overwritten files restored
+    #[command(visible_alias = "ud")]
+    Undo,
+    /// Remove previously generated template instance, values are set same as for use
+    #[command(visible_alias = "ds")]
+    Destroy(UseArgs),
+    /// Print shell completion script, e.g. gen completions zsh > ~/.zfunc/_gen
+    Completions {
+        /// Shell to print completion script for
+        #[arg(value_parser = COMPLETION_SHELLS)]
+        shell: String,
+    },
+    /// Show version
+    #[command(visible_alias = "v")]
     Version,
-    Fetch,
-    VariablesList,
 }
-impl Commands {
-    pub fn command_str(&self) -> String {
-        match self {
-            Commands::New => "new".to_owned(),
-            Commands::Edit => "edit".to_owned(),
-            Commands::Delete => "delete".to_owned(),
-            Commands::Publish => "publish".to_owned(),
-            Commands::Unpublish => "unpublish".to_owned(),
-            Commands::Help => "help".to_owned(),
-            Commands::Version => "version".to_owned(),
-            Commands::Global => "--global".to_owned(),
-            Commands::Refresh => "refresh".to_owned(),
-            Commands::Use => "use".to_owned(),
-            Commands::Fetch => "fetch".to_owned(),
-            Commands::VariablesList => "variables".to_owned(),
+
+impl Cli {
+    /**
+     * Parse arguments, `gen <template> [name] ...` is parsed as `gen use <template> [name] ...`.
+     */
+    pub fn try_parse_args<I, T>(arguments: I) -> Result<CliArgs, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let cli = Cli::try_parse_from(arguments)?;
+        let shortcut_arguments = match cli.command {
+            Some(CliCommand::External(shortcut_arguments)) => shortcut_arguments,
+            Some(CliCommand::Command(command)) => {
+                return Ok(CliArgs {
+                    global: cli.global,
+                    command: Some(command),
+                })
+            }
+            None => {
+                return Ok(CliArgs {
+                    global: cli.global,
+                    command: None,
+                })
+            }
+        };
+        let use_arguments = ["gen", "use"]
+            .iter()
+            .map(|item| item.to_string())
+            .chain(shortcut_arguments);
+        let shortcut = Cli::try_parse_from(use_arguments)?;
+        let command = match shortcut.command {
+            Some(CliCommand::Command(command)) => Some(command),
+            _ => None,
+        };
+        Ok(CliArgs {
+            global: cli.global || shortcut.global,
+            command,
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Case type of file names, e.g. kebab, kebab-case or KebabCase
+    #[arg(long, value_parser = TemplateInit::parse_case_type)]
+    pub file_case: Option<CaseType>,
+    /// Case type of file content, e.g. pascal, PascalCase
+    #[arg(long, value_parser = TemplateInit::parse_case_type)]
+    pub content_case: Option<CaseType>,
+    /// Command to open template files in editor, e.g. code
+    #[arg(long)]
+    pub editor: Option<String>,
+    /// Never prompt, missing values keep existing or default ones
+    #[arg(short = 'n', long)]
+    pub no_input: bool,
+}
+
+impl InitArgs {
+    pub fn to_options(&self) -> TemplateInitOptions {
+        TemplateInitOptions {
+            file_case: self.file_case.to_owned(),
+            content_case: self.content_case.to_owned(),
+            editor: self.editor.to_owned(),
+            no_input: self.no_input,
         }
     }
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    /// Git url, project directory or its .gen folder, .tar.gz / .zip file or http(s) url.
+    /// Subdirectory can be appended as source#subdir
+    pub source: String,
+    /// Branch, tag or commit sha to fetch, git sources only
+    #[arg(long = "ref", value_name = "REF")]
+    pub git_ref: Option<String>,
+    /// Subdirectory of the source with templates, or with single template
+    #[arg(long = "path", value_name = "SUBDIR")]
+    pub subdir: Option<String>,
+    /// Import single template under another name, e.g. button or acme/button
+    #[arg(long = "as", value_name = "NAME")]
+    pub name: Option<String>,
+}
 
-    pub fn command_str_short(&self) -> String {
-        match self {
-            Commands::New => "n".to_owned(),
-            Commands::Edit => "e".to_owned(),
-            Commands::Delete => "d".to_owned(),
-            Commands::Publish => "p".to_owned(),
-            Commands::Unpublish => "u".to_owned(),
-            Commands::Help => "h".to_owned(),
-            Commands::Version => "v".to_owned(),
-            Commands::Global => "-g".to_owned(),
-            Commands::Refresh => "r".to_owned(),
-            Commands::Use => "u".to_owned(),
-            Commands::Fetch => "f".to_owned(),
-            Commands::VariablesList => "vv".to_owned(),
+impl FetchArgs {
+    pub fn to_options(&self) -> TemplateFetchOptions {
+        TemplateFetchOptions {
+            git_ref: self.git_ref.to_owned(),
+            subdir: self.subdir.to_owned(),
+            name: self.name.to_owned(),
         }
     }
+}
+
+#[derive(Debug, Args)]
+pub struct PublishArgs {
+    /// Template name, selected from list when missing
+    #[arg(add = ArgValueCandidates::new(CliCompletions::templates))]
+    pub template: Option<String>,
+    /// Git url or path of repository with shared templates, works with local bare repository too
+    #[arg(long, value_name = "REPOSITORY")]
+    pub to: String,
+    /// Commit message
+    #[arg(short, long)]
+    pub message: Option<String>,
+}
 
-    pub fn command_description(&self) -> String {
-        let support_global = "Support global flag, second argument [template_name]";
-        match self {
-            Commands::New => format!("Create new template. If running local template, template folder will be generated to current (cwd) path. {}", support_global).to_owned(),
-            Commands::Edit => format!("Edit template. {}", support_global).to_owned(),
-            Commands::Delete => format!("Delete template. {}", support_global).to_owned(),
-            Commands::Publish => format!("Publish template. {}", support_global).to_owned(),
-            Commands::Unpublish => format!("Unpublish template. {}", support_global).to_owned(),
-            Commands::Help => "Show help".to_owned(),
-            Commands::Version => "Show version".to_owned(),
-            Commands::Global => "Global scope".to_owned(),
-            Commands::Refresh => format!(
-                "Refresh templates all templates.{}",
-                support_global
-            )
-            .to_owned(),
-            Commands::Use => format!("Use / template. {}", support_global).to_owned(),
-            Commands::Fetch => format!(
-                "Fetch templates from github url (todo any url). {}",
-                support_global
-            )
-            .to_owned(),
-            Commands::VariablesList => "List of all templates variables".to_string().to_owned(),
+impl PublishArgs {
+    pub fn to_options(&self) -> TemplatePublishOptions {
+        TemplatePublishOptions {
+            to: self.to.to_owned(),
+            message: self.message.to_owned(),
         }
     }
+}
 
-    pub fn print_usage_item(command: Commands) {
-        let width = 10;
-        let width2 = 2;
-        println!(
-            "{:width$} short: {:width2$} -> {}",
-            command.command_str().bold().green(),
-            command.command_str_short().bold().green(),
-            command.command_description(),
-        );
+#[derive(Debug, Args)]
+pub struct UseArgs {
+    /// Template name, selected from list when missing. Remote template can be used without
+    /// fetching it, e.g. git+file:///path/repo#component or https://github.com/acme/templates#component
+    #[arg(add = ArgValueCandidates::new(CliCompletions::templates))]
+    pub template: Option<String>,
+    /// Value of unnamed __var__, e.g. gen use component Button
+    pub name: Option<String>,
+    /// Set template variable value, e.g. --set __var__name=Button. Can be repeated
+    #[arg(
+        short,
+        long = "set",
+        value_name = "KEY=VALUE",
+        add = ArgValueCompleter::new(CliCompletions::set_values)
+    )]
+    pub set: Vec<String>,
+    /// Load template variable values from json / toml file, or - for stdin
+    #[arg(short, long, value_name = "FILE")]
+    pub answers: Vec<String>,
+    /// Never prompt, fail with list of unresolved variables instead
+    #[arg(short = 'n', long)]
+    pub no_input: bool,
+    /// Overwrite existing files without asking, needed with --no-input when files exist
+    #[arg(long)]
+    pub overwrite: bool,
+    /// Preview without writing anything, show created / overwritten / appended files with diff
+    #[arg(short, long)]
+    pub dry_run: bool,
+    /// Use cached version of remote template (e.g. git+https://...#template) without connecting
+    #[arg(long)]
+    pub offline: bool,
+}
+
+impl UseArgs {
+    /**
+     * Name is applied first, then answers files, so `--set` values can override them.
+     */
+    pub fn to_options(&self) -> Result<TemplateUseOptions, String> {
+        let mut answers = TemplateAnswers::new();
+        if let Some(name) = &self.name {
+            answers.insert(TEMPLATE_VARIABLE, name)?;
+        }
+        for answers_argument in self.answers.iter() {
+            answers.merge(&TemplateAnswers::load(answers_argument)?);
+        }
+        answers.merge(&TemplateAnswers::from_set_arguments(&self.set)?);
+
+        Ok(TemplateUseOptions {
+            answers,
+            name: self.name.to_owned(),
+            no_input: self.no_input,
+            overwrite: self.overwrite,
+            dry_run: self.dry_run,
+        })
     }
+}
 
-    pub fn print_help() {
+impl Commands {
+    /**
+     * Guide printed when gen is run without command, followed by list of commands.
+     */
+    pub fn print_guide() {
         println!();
         println!("{}", "🤷🏻 How it works?".bold().magenta());
         println!();
         println!(
             "Easy to use template generator for any code any project with support of having private / public github templates, website templates & more.\nTo quick start start with {} and then {} commands. To see more, check out the usage below:",  
             "new".bold().green(),
-            "select".bold().green(),
+            "use".bold().green(),
 
         );
 
@@ -120,68 +337,133 @@ impl Commands {
             "__select__".green().bold(),
         );
         println!(
-            "{:width$}: __var__ and __select__ also support casing. kebab__, snake__, camel__, pascal__.\nFor example __var__kebab__ or __select___author__camel__. So case words are reserved.",
+            "{:width$}: __var__ and __select__ also support casing. kebab__, snake__, camel__, pascal__, screaming__, title__, dot__, path__, flat__, train__, lower__, upper__.\nFor example __var__kebab__ or __select___author__camel__. So case words are reserved.",
             "Case support".green().bold(),
         );
-        println!();
-        println!("{}", "🧩 Commands:".bold().magenta());
-        println!();
-        Commands::print_usage_item(Commands::New);
-        Commands::print_usage_item(Commands::Edit);
-        Commands::print_usage_item(Commands::Delete);
-        // Commands::print_usage_item(Commands::Publish);
-        // Commands::print_usage_item(Commands::Unpublish);
-        Commands::print_usage_item(Commands::Fetch);
-        Commands::print_usage_item(Commands::Help);
-        Commands::print_usage_item(Commands::Version);
-        Commands::print_usage_item(Commands::Refresh);
-        Commands::print_usage_item(Commands::Use);
-        Commands::print_usage_item(Commands::VariablesList);
-        Commands::print_usage_item(Commands::Global);
-
-        println!();
-        println!();
         println!(
-            "For more info visit: {}",
-            TEMPLATE_DOCS_URL.yellow().underline().bold()
+            "{:width$}: Yes / no variable, like __bool__stories__. Value is true or false.",
+            "__bool__".green().bold(),
+        );
+        println!(
+            "{:width$}: Comma separated values, like __list__fields__. In path it generates one file per item.",
+            "__list__".green().bold(),
+        );
+        println!(
+            "{:width$}: __each__fields__ ... __endeach__ repeats content per item, with __index__, __if__first__ and __if__last__ helpers.",
+            "Repeat".green().bold(),
+        );
+        println!(
+            "{:width$}: __if__select__kind__complex__ ... __elif__bool__stories__ ... __else__ ... __endif__\nIn paths only __if__ is supported, file is not generated when condition is not met.",
+            "Conditions".green().bold(),
         );
         println!();
+        Cli::command().print_help().unwrap();
     }
+}
 
-    pub fn is_command_from_set(&self, arguments: &HashSet<String>) -> bool {
-        arguments.contains(&self.command_str()) || arguments.contains(&self.command_str_short())
-    }
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Commands};
+    use crate::{case_util::CaseType, template_variable::TemplateVariableInfo};
+    use clap::Parser;
+
+    #[test]
+    fn should_parse_subcommands() {
+        let cli = Cli::try_parse_args([
+            "gen",
+            "use",
+            "component",
+            "-s",
+            "name=Button",
+            "--dry-run",
+            "-g",
+        ])
+        .unwrap();
+        assert!(cli.global);
+        match cli.command {
+            Some(Commands::Use(use_args)) => {
+                assert_eq!(use_args.template.as_deref(), Some("component"));
+                assert_eq!(use_args.set, vec!["name=Button".to_string()]);
+                assert!(use_args.dry_run);
+                assert!(!use_args.no_input);
+            }
+            _ => panic!("expected use command"),
+        }
+
+        let cli = Cli::try_parse_args([
+            "gen",
+            "fetch",
+            "https://x.git",
+            "--ref",
+            "v1",
+            "--as",
+            "acme/button",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Fetch(fetch_args)) => {
+                assert_eq!(fetch_args.source, "https://x.git");
+                let options = fetch_args.to_options();
+                assert_eq!(options.git_ref.as_deref(), Some("v1"));
+                assert_eq!(options.name.as_deref(), Some("acme/button"));
+            }
+            _ => panic!("expected fetch command"),
+        }
 
-    pub fn is_command(argument: &str) -> bool {
-        let commands = vec![
-            Commands::New,
-            Commands::Edit,
-            Commands::Delete,
-            Commands::Publish,
-            Commands::Unpublish,
-            Commands::Help,
-            Commands::Version,
-            Commands::Global,
-            Commands::Refresh,
-            Commands::Use,
-            Commands::Fetch,
-            Commands::VariablesList,
-        ];
-        for command in commands {
-            if argument == command.command_str() || argument == command.command_str_short() {
-                return true;
+        let cli = Cli::try_parse_args(["gen", "update", "button", "--ref", "v2"]).unwrap();
+        match cli.command {
+            Some(Commands::Update {
+                template, git_ref, ..
+            }) => {
+                assert_eq!(template.as_deref(), Some("button"));
+                assert_eq!(git_ref.as_deref(), Some("v2"));
             }
+            _ => panic!("expected update command"),
+        }
+        assert!(Cli::try_parse_args(["gen", "update", "--ref", "v2"]).is_err());
+
+        let cli = Cli::try_parse_args(["gen", "init", "--file-case", "kebab"]).unwrap();
+        match cli.command {
+            Some(Commands::Init(init_args)) => {
+                assert_eq!(init_args.file_case, Some(CaseType::KebabCase));
+            }
+            _ => panic!("expected init command"),
         }
-        false
     }
 
-    pub fn return_unknown_arguments(arguments: &Vec<String>) -> Vec<String> {
-        let mut unknown_arguments = vec![];
-        for argument in arguments {
-            if !Commands::is_command(argument) {
-                unknown_arguments.push(argument.to_string());
+    #[test]
+    fn should_parse_template_shortcut() {
+        let cli = Cli::try_parse_args([
+            "gen",
+            "component",
+            "Button",
+            "-n",
+            "--set",
+            "kind=basic",
+            "-g",
+        ])
+        .unwrap();
+        assert!(cli.global);
+        match cli.command {
+            Some(Commands::Use(use_args)) => {
+                assert_eq!(use_args.template.as_deref(), Some("component"));
+                assert_eq!(use_args.name.as_deref(), Some("Button"));
+                assert!(use_args.no_input);
+
+                let options = use_args.to_options().unwrap();
+                let variable = TemplateVariableInfo::from_str("__var__").unwrap();
+                assert_eq!(options.answers.get(&variable).unwrap(), "Button");
+                assert_eq!(options.name.as_deref(), Some("Button"));
             }
+            _ => panic!("expected use command"),
         }
-        unknown_arguments
+
+        assert!(Cli::try_parse_args(["gen", "component", "Button", "--bogus"]).is_err());
+    }
+
+    #[test]
+    fn should_reject_unknown_arguments() {
+        assert!(Cli::try_parse_from(["gen", "use", "--bogus"]).is_err());
+        assert!(Cli::try_parse_from(["gen", "init", "--file-case", "nope"]).is_err());
     }
 }
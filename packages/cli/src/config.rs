@@ -1,61 +1,69 @@
 use crate::{
-    actions::TemplateAction,
-    constants::CONFIG_FILE,
+    constants::{CONFIG_FILE, TEMPLATE_ROOT_FOLDER},
     template::{TemplateCaseType, TemplateFolder},
 };
+use colored::Colorize;
 
 use serde::{Deserialize, Serialize};
 use std::{
-    fs,
+    env, fs,
     path::{Path, PathBuf},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 
 pub struct ConfigFile {
-    pub case_type: TemplateCaseType,
+    pub case_type: Option<TemplateCaseType>,
     pub open_editor_command: Option<String>,
 }
 
 impl ConfigFile {
     pub fn new() -> ConfigFile {
         ConfigFile {
-            case_type: TemplateCaseType::new(),
+            case_type: None,
             open_editor_command: None,
         }
     }
 
-    pub fn load_config(directory: &Path, is_template_enabled: bool) -> ConfigFile {
+    /**
+     * Load config, defaults are used when there is no config yet.
+     * Nothing is created here, config is written only by `gen init`.
+     */
+    pub fn load_config(directory: &Path) -> ConfigFile {
         let config_path = directory.join(CONFIG_FILE);
         let config_content = fs::read_to_string(&config_path);
 
         if config_content.is_err() {
-            if !is_template_enabled {
-                return ConfigFile::new();
-            }
-            let config = TemplateAction::get_template_config();
-            config.save_config(directory);
-            return config;
+            return ConfigFile::new();
         }
         let config_content = config_content.unwrap();
         let config: Result<ConfigFile, serde_json::Error> = serde_json::from_str(&config_content);
 
-        if config.is_err() {
-            let config = TemplateAction::get_template_config();
-            fs::remove_file(&config_path).unwrap();
-            config.save_config(directory);
-            return config;
+        if let Err(error) = config {
+            println!(
+                "{} {} {}",
+                "🚨 Invalid config".yellow(),
+                config_path.to_str().unwrap().bold(),
+                format!("({}), using defaults. Run gen init to fix it.", error).yellow()
+            );
+            return ConfigFile::new();
         }
 
         config.unwrap()
     }
 
-    pub fn save_config(&self, directory: &Path) {
+    pub fn save_config(&self, directory: &Path) -> Result<(), String> {
         let config_path = directory.join(CONFIG_FILE);
         let config_content = serde_json::to_string_pretty(&self).unwrap();
-        fs::write(config_path, config_content).unwrap();
+        fs::create_dir_all(directory)
+            .and_then(|_| fs::write(&config_path, config_content))
+            .map_err(|error| format!("Cannot save config {}: {}", config_path.display(), error))
     }
 
+    /**
+     * Editor command falls back to the other config, case types are resolved by
+     * `TemplateCaseResolver` level by level.
+     */
     pub fn merge(&mut self, config: &ConfigFile) {
         if self.open_editor_command.is_none() {
             self.open_editor_command = config.open_editor_command.clone();
@@ -71,30 +79,160 @@ pub struct Config {
 }
 
 impl Config {
-    pub fn load_template_folders(directory: &PathBuf) -> Config {
-        if !directory.exists() {
-            fs::create_dir_all(directory).unwrap();
+    /**
+     * Project templates folder, `.gen` in current directory.
+     */
+    pub fn local_path() -> PathBuf {
+        env::current_dir().unwrap().join(TEMPLATE_ROOT_FOLDER)
+    }
+
+    /**
+     * Global templates folder, `~/.gen`.
+     */
+    pub fn global_path() -> PathBuf {
+        Path::new(&env::var("HOME").unwrap()).join(TEMPLATE_ROOT_FOLDER)
+    }
+
+    pub fn load_template_folders(directory: &Path) -> Config {
+        // search directory and get all folders, missing directory means no templates yet
+        let folders = Config::read_folders(directory);
+        if folders.is_err() {
+            return Config {
+                template_folders: Vec::new(),
+                config: ConfigFile::new(),
+                path: directory.to_path_buf(),
+            };
         }
-        // search directory and get all folders
-        let entries = fs::read_dir(directory).unwrap();
-        let folders = entries
+        let mut folders = folders.unwrap();
+        folders.sort();
+
+        let mut template_folders = Vec::new();
+        for (name, path) in folders {
+            if !Config::is_namespace(&path) {
+                template_folders.push(TemplateFolder { name, path });
+                continue;
+            }
+            // one level of namespaces, e.g. `.gen/acme/component` is template `acme/component`
+            let mut namespace_folders = Config::read_folders(&path).unwrap_or_default();
+            namespace_folders.sort();
+            for (template_name, template_path) in namespace_folders {
+                template_folders.push(TemplateFolder {
+                    name: format!("{}/{}", name, template_name),
+                    path: template_path,
+                });
+            }
+        }
+        let folders = template_folders;
+
+        Config {
+            template_folders: folders,
+            config: ConfigFile::new(),
+            path: directory.to_path_buf(),
+        }
+    }
+
+    /**
+     * Visible sub folders with their names, hidden folders are used by gen itself,
+     * for example generation history.
+     */
+    fn read_folders(directory: &Path) -> Result<Vec<(String, PathBuf)>, std::io::Error> {
+        let folders = fs::read_dir(directory)?
             .filter_map(|entry| {
-                let entry = entry.unwrap();
+                let entry = entry.ok()?;
                 let path = entry.path();
-                if path.is_dir() {
-                    return Some(TemplateFolder {
-                        name: path.file_name().unwrap().to_str().unwrap().to_string(),
-                        path,
-                    });
+                let name = entry.file_name().to_str()?.to_string();
+                if path.is_dir() && !name.starts_with('.') {
+                    return Some((name, path));
                 }
                 None
             })
+            .collect();
+        Ok(folders)
+    }
+
+    /**
+     * Template named `namespace/name` can be added only when namespace folder is missing,
+     * or it's not a template itself, so it never ends up nested in another template.
+     * Name is expected to be validated by `TemplateFolder::validate_name` already.
+     */
+    pub fn can_add_template(directory: &Path, template_name: &str) -> bool {
+        let Some((namespace, _)) = template_name.split_once('/') else {
+            return true;
+        };
+        let path = directory.join(namespace);
+        if !path.exists() {
+            return true;
+        }
+        let has_files = fs::read_dir(&path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .any(|entry| !entry.path().is_dir())
+            })
+            .unwrap_or(true);
+        path.is_dir() && !has_files
+    }
+
+    /**
+     * Namespace is a folder with template folders only, it has no `_.json` or other files.
+     */
+    fn is_namespace(path: &Path) -> bool {
+        if path.join(CONFIG_FILE).exists() {
+            return false;
+        }
+        let entries = fs::read_dir(path);
+        if entries.is_err() {
+            return false;
+        }
+        let entries = entries
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
             .collect::<Vec<_>>();
+        let has_files = entries.iter().any(|entry| !entry.is_dir());
+        let has_templates = entries
+            .iter()
+            .any(|entry| entry.join(CONFIG_FILE).is_file());
+        !has_files && has_templates
+    }
+}
 
-        Config {
-            template_folders: folders,
-            config: ConfigFile::new(),
-            path: directory.to_path_buf(),
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::{template::TemplateFolder, template_git::TemplateGit};
+    use std::fs;
+
+    #[test]
+    fn should_load_namespaced_templates() {
+        let path = TemplateGit::temp_directory("test-config").unwrap();
+        for template in ["component", "acme/component", "acme/hook", ".history/x"] {
+            fs::create_dir_all(path.join(template)).unwrap();
+            fs::write(path.join(template).join("_.json"), "{}").unwrap();
+        }
+        // template with nested folders only is not a namespace
+        fs::create_dir_all(path.join("page/src")).unwrap();
+        fs::write(path.join("page/_.json"), "{}").unwrap();
+
+        let names = Config::load_template_folders(&path)
+            .template_folders
+            .into_iter()
+            .map(|template_folder| template_folder.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["acme/component", "acme/hook", "component", "page"]);
+
+        assert!(Config::can_add_template(&path, "acme/button"));
+        assert!(Config::can_add_template(&path, "other/button"));
+        assert!(Config::can_add_template(&path, "button"));
+        assert!(!Config::can_add_template(&path, "component/button"));
+        assert!(!Config::can_add_template(&path, "page/button"));
+
+        assert!(TemplateFolder::validate_name("acme/component").is_ok());
+        for name in [
+            "", "a/b/c", "acme/", ".hidden", "acme/.x", "..", "../src", "/src", "c:x",
+        ] {
+            assert!(TemplateFolder::validate_name(name).is_err());
         }
+        fs::remove_dir_all(&path).unwrap();
     }
 }
@@ -12,10 +12,17 @@ pub const TEMPLATE_DOCS_URL: &str = "https://github.com/samuelgja/gen";
 pub const TEMPLATE_VARIABLE: &str = "__var__";
 pub const TEMPLATE_SELECT: &str = "__select__";
 pub const TEMPLATE_ROOT_FOLDER: &str = ".gen";
+pub const TEMPLATE_HISTORY_FOLDER: &str = ".history";
+pub const TEMPLATE_LOCK_FILE: &str = "gen.lock";
+/// remote templates used without fetching, in ~/.gen
+pub const TEMPLATE_CACHE_FOLDER: &str = ".cache";
+/// env variable used by completion scripts to call back into gen
+pub const COMPLETE_VAR: &str = "COMPLETE";
+pub const COMPLETION_SHELLS: [&str; 4] = ["bash", "zsh", "fish", "elvish"];
 
 lazy_static! {
     // it can match TEMPLATE_VARIABLE or TEMPLATE_VARIABLE + any number
     // it can also be in format - __var__1 or __var__2 or
-    pub static ref TEMPLATE_VARIABLE_REGEX: Regex = Regex::new(r"(__var__|__select__)((?:[a-zA-Z0-9]+__))?((?:[a-zA-Z0-9]+__))?((?:[a-zA-Z0-9]+__))?").unwrap();
+    pub static ref TEMPLATE_VARIABLE_REGEX: Regex = Regex::new(r"(__var__|__select__|__bool__|__list__|__if__|__elif__|__else__|__endif__|__each__|__endeach__|__index__)((?:[a-zA-Z0-9]+__))?((?:[a-zA-Z0-9]+__))?((?:[a-zA-Z0-9]+__))?").unwrap();
 
 }
@@ -1,36 +1,34 @@
 mod actions;
 mod case_util;
 mod cli_commands;
+mod cli_completions;
 mod cli_parser;
 mod commands;
 mod config;
 mod constants;
 mod search_folder;
 mod template;
+mod template_answers;
+mod template_auto;
+mod template_cache;
+mod template_case;
 mod template_file_content;
+mod template_git;
+mod template_insert;
+mod template_lock;
+mod template_manifest;
+mod template_merge;
+mod template_plan;
+mod template_register;
+mod template_render;
+mod template_source;
 mod template_variable;
 
 use cli_parser::CliParser;
 
 /**
  * CLI TOOL - name gen - but gen is name of the tool, so it will be in bash profile.
- * Template arguments:
- * - empty - will show list of templates and after select, it will go to template name selection
- * - [template name] - will go to template name selection
- * - [template name] [custom name] - will go to template file select
- * - new - will go new to template editor
- *
- * Flags for template arguments:
- * --edit -e - will go to template editor
- * --delete -d - will delete template
- * --publish -p - will publish template to github
- * --unpublish -u - will unpublish template from github
- *
- * Global flags:
- * --help -h - will show help
- * --version -v - will show version
- * --list -l - will show list of templates - but same as empty template argument
- * --search -s - will search for templates on github
+ * Commands are parsed in `CliParser`, see `Commands` for the list of subcommands.
  */
 fn main() {
     CliParser::parse();
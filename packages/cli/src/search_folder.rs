@@ -18,7 +18,6 @@ pub struct SearchItem {
 pub struct SearchResult {
     pub files: Vec<SearchItem>,
     pub variables: IndexMap<String, TemplateVariableInfo>,
-    pub is_within_one_folder: bool,
     pub template_config: TemplateConfig,
 }
 
@@ -68,45 +67,95 @@ impl SearchFolder {
             return SearchResult {
                 files,
                 variables,
-                is_within_one_folder: false,
                 template_config: TemplateConfig::load_template_config_from_path(template_path),
             };
         }
 
-        let start_dir_path = files[0].path.parent().unwrap().to_path_buf();
-        let mut is_within_one_folder = true;
+        let mut tokens = Vec::new();
         for file in files.iter() {
-            let content = std::fs::read_to_string(&file.path).unwrap();
-            for variable in TemplateVariableInfo::parse_iter(&content) {
-                let key = SearchFolder::get_key(&variable);
-                variables.insert(key, variable);
-            }
-
-            if is_within_one_folder && !file.path.starts_with(&start_dir_path) {
-                is_within_one_folder = false;
+            // config is not rendered, keys like `auto_values` would be picked as variables
+            if file.is_config {
+                continue;
             }
+            let content = std::fs::read_to_string(&file.path).unwrap();
+            tokens.extend(TemplateVariableInfo::parse_iter(&content));
 
             // iter over path parts
             for part in file.template_path.iter() {
                 let part = part.to_str().unwrap();
-
-                for variable in TemplateVariableInfo::parse_iter(part) {
-                    let key = SearchFolder::get_key(&variable);
-                    variables.insert(key, variable);
-                }
+                tokens.extend(TemplateVariableInfo::parse_iter(part));
             }
         }
+        for token in tokens.iter() {
+            SearchFolder::insert_token(&mut variables, token.to_owned());
+        }
+
+        // `__var__title__` is variable `title` when template names it anywhere else
+        let named_variables = variables.clone();
+        variables.clear();
+        for token in tokens {
+            let token = SearchFolder::resolve_name(token, |key| named_variables.contains_key(key));
+            SearchFolder::insert_token(&mut variables, token);
+        }
 
         variables.sort_keys();
 
         SearchResult {
             files,
             variables,
-            is_within_one_folder,
             template_config: TemplateConfig::load_template_config_from_path(template_path),
         }
     }
 
+    /**
+     * Block tokens are not variables, but variables checked by their conditions
+     * and lists repeated by `__each__` have to be resolved.
+     */
+    fn insert_token(
+        variables: &mut IndexMap<String, TemplateVariableInfo>,
+        variable: TemplateVariableInfo,
+    ) {
+        if variable.template_variable.is_value() {
+            SearchFolder::insert_variable(variables, variable);
+            return;
+        }
+        let condition = variable.condition.as_ref();
+        let condition = condition.filter(|condition| condition.template_variable.is_value());
+        if let Some(condition) = condition {
+            SearchFolder::insert_variable(variables, condition.to_variable_info());
+        }
+    }
+
+    /**
+     * Variable is auto only when all its occurrences are auto, otherwise it's prompted
+     * and auto occurrences just reuse the value.
+     */
+    fn insert_variable(
+        variables: &mut IndexMap<String, TemplateVariableInfo>,
+        mut variable: TemplateVariableInfo,
+    ) {
+        let key = SearchFolder::get_key(&variable);
+        if let Some(existing) = variables.get(&key) {
+            variable.is_auto = variable.is_auto && existing.is_auto;
+        }
+        variables.insert(key, variable);
+    }
+
+    /**
+     * Token with case suffix which used to be a name, like `__var__title__`, is the named
+     * variable when it exists, otherwise it's the case of unnamed variable.
+     */
+    pub fn resolve_name(
+        variable: TemplateVariableInfo,
+        has_key: impl Fn(&str) -> bool,
+    ) -> TemplateVariableInfo {
+        let named = variable.as_named();
+        if named.var_name != variable.var_name && has_key(&SearchFolder::get_key(&named)) {
+            return named;
+        }
+        variable
+    }
+
     pub fn get_key(variable: &TemplateVariableInfo) -> String {
         format!("{}_{}", variable.template_variable, variable.var_name,)
     }
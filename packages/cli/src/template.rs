@@ -1,4 +1,10 @@
-use crate::{case_util::CaseType, config::Config, constants::CONFIG_FILE};
+use crate::{
+    case_util::CaseType,
+    config::Config,
+    constants::CONFIG_FILE,
+    template_insert::{TemplateDedupe, TemplateInsert},
+    template_merge::TemplateMerge,
+};
 
 use serde::{Deserialize, Serialize};
 use std::{
@@ -10,7 +16,9 @@ use std::{
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TemplateCaseType {
+    #[serde(default)]
     pub content: CaseType,
+    #[serde(default)]
     pub file: CaseType,
 }
 impl TemplateCaseType {
@@ -49,6 +57,28 @@ impl TemplateFolder {
             path: template_path.clone(),
         }
     }
+
+    /**
+     * Template name can contain one level of namespace, e.g. `component` or `acme/component`.
+     * Name is joined to templates folder, so it can never point outside of it.
+     */
+    pub fn validate_name(name: &str) -> Result<(), String> {
+        let parts = name.split('/').collect::<Vec<_>>();
+        let is_valid = parts.len() <= 2
+            && parts.iter().all(|part| {
+                !part.is_empty()
+                    && *part != ".."
+                    && !part.starts_with('.')
+                    && !part.contains(['\\', ':'])
+            });
+        if !is_valid {
+            return Err(format!(
+                "Invalid template name '{}', expected name or namespace/name",
+                name
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl Display for TemplateFolder {
@@ -67,12 +97,36 @@ impl TemplateFolder {
         fs::write(path, content).unwrap();
     }
 }
+/**
+ * Options of single template file, keyed by its path in template folder, e.g. `src/index.ts`.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TemplateFileConfig {
+    /// where content is inserted when file already exists, file is always inserted into when set
+    pub insert: Option<TemplateInsert>,
+    /// how content already present in the file is skipped, `fragment` by default
+    pub dedupe: Option<TemplateDedupe>,
+    /// deep merge into existing JSON, YAML or TOML file instead of inserting text
+    pub merge: Option<TemplateMerge>,
+    /// declare file in parent `mod.rs` / `index.ts`, e.g. `pub mod name;`
+    pub register: Option<bool>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TemplateConfig {
     pub name: String,
     pub description: String,
     pub case_type: Option<TemplateCaseType>,
+    /// case types for single variables, keyed by variable (`__var__name`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable_case_types: Option<HashMap<String, TemplateCaseType>>,
     pub select_options: Option<HashMap<String, Vec<String>>>,
+    /// expressions for `auto__` variables, keyed by variable (`__var__slug`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_values: Option<HashMap<String, String>>,
+    /// per file options, keyed by file path in template folder with `/` separator
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<HashMap<String, TemplateFileConfig>>,
 }
 
 impl TemplateConfig {
@@ -81,7 +135,10 @@ impl TemplateConfig {
             name: "".to_string(),
             description: "".to_string(),
             case_type: None,
+            variable_case_types: None,
             select_options: None,
+            auto_values: None,
+            files: None,
         }
     }
 
@@ -106,6 +163,18 @@ impl TemplateConfig {
         fs::write(config_path, config_content).unwrap();
     }
 
+    /**
+     * Options of template file, `template_path` is relative to template folder.
+     */
+    pub fn file_config(&self, template_path: &Path) -> Option<&TemplateFileConfig> {
+        let key = template_path
+            .iter()
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.files.as_ref()?.get(&key)
+    }
+
     pub fn merge_select_options(&mut self, select_options: &HashMap<String, Vec<String>>) {
         if self.select_options.is_none() {
             self.select_options = Some(HashMap::new());
@@ -117,3 +186,15 @@ impl TemplateConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateConfig;
+
+    #[test]
+    fn should_not_save_missing_options() {
+        let content = serde_json::to_string(&TemplateConfig::new()).unwrap();
+        assert!(!content.contains("auto_values"));
+        assert!(!content.contains("variable_case_types"));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    search_folder::SearchFolder, template_render::TemplateRender,
+    template_variable::TemplateVariableInfo,
+};
+use indexmap::IndexMap;
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/**
+ * Pre-filled values for template variables, coming from `--set key=value` flags
+ * or from `--answers file.json|file.toml|-` files.
+ *
+ * Keys can be written as template tokens (`__var__name`, `__select__kind__`) or as bare
+ * variable names (`name`), bare names match both __var__ and __select__ variables.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct TemplateAnswers {
+    pub values: IndexMap<String, String>,
+}
+
+impl TemplateAnswers {
+    pub fn new() -> TemplateAnswers {
+        TemplateAnswers {
+            values: IndexMap::new(),
+        }
+    }
+
+    pub fn from_set_arguments(arguments: &[String]) -> Result<TemplateAnswers, String> {
+        let mut answers = TemplateAnswers::new();
+        for argument in arguments {
+            let split = argument.split_once('=');
+            if split.is_none() {
+                return Err(format!(
+                    "Invalid --set value '{}', expected format key=value",
+                    argument
+                ));
+            }
+            let (key, value) = split.unwrap();
+            answers.insert(key, value)?;
+        }
+        Ok(answers)
+    }
+
+    pub fn load(source: &str) -> Result<TemplateAnswers, String> {
+        if source == "-" {
+            let mut content = String::new();
+            let result = io::stdin().read_to_string(&mut content);
+            if let Err(error) = result {
+                return Err(format!("Cannot read answers from stdin: {}", error));
+            }
+            return TemplateAnswers::from_content(&content, None);
+        }
+
+        let path = Path::new(source);
+        let content = fs::read_to_string(path);
+        if let Err(error) = content {
+            return Err(format!("Cannot read answers file {}: {}", source, error));
+        }
+        let extension = path.extension().and_then(|item| item.to_str());
+        TemplateAnswers::from_content(&content.unwrap(), extension)
+    }
+
+    pub fn from_content(content: &str, extension: Option<&str>) -> Result<TemplateAnswers, String> {
+        let values = match extension {
+            Some("json") => TemplateAnswers::parse_json(content)?,
+            Some("toml") => TemplateAnswers::parse_toml(content)?,
+            // stdin or unknown extension, try both formats
+            _ => match TemplateAnswers::parse_json(content) {
+                Ok(values) => values,
+                Err(_) => TemplateAnswers::parse_toml(content)
+                    .map_err(|_| "Answers are neither valid JSON nor TOML".to_string())?,
+            },
+        };
+
+        let mut answers = TemplateAnswers::new();
+        for (key, value) in values {
+            answers.insert(&key, &value)?;
+        }
+        Ok(answers)
+    }
+
+    fn parse_json(content: &str) -> Result<Vec<(String, String)>, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(content).map_err(|error| error.to_string())?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| "Answers JSON has to be an object".to_string())?;
+
+        let mut values = Vec::new();
+        for (key, value) in object {
+            let value = match value {
+                serde_json::Value::String(value) => value.to_owned(),
+                serde_json::Value::Number(value) => value.to_string(),
+                serde_json::Value::Bool(value) => value.to_string(),
+                // list values, items are stored comma separated
+                serde_json::Value::Array(items) => {
+                    let items = items
+                        .iter()
+                        .map(|item| match item {
+                            serde_json::Value::String(item) => Some(item.to_owned()),
+                            serde_json::Value::Number(item) => Some(item.to_string()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    if items.is_none() {
+                        return Err(format!("Unsupported answer value for key '{}'", key));
+                    }
+                    TemplateRender::join_list(&items.unwrap())
+                }
+                _ => return Err(format!("Unsupported answer value for key '{}'", key)),
+            };
+            values.push((key.to_owned(), value));
+        }
+        Ok(values)
+    }
+
+    fn parse_toml(content: &str) -> Result<Vec<(String, String)>, String> {
+        let table: toml::Table = toml::from_str(content).map_err(|error| error.to_string())?;
+
+        let mut values = Vec::new();
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                toml::Value::Array(items) => {
+                    let items = items
+                        .iter()
+                        .map(|item| match item {
+                            toml::Value::String(item) => Some(item.to_owned()),
+                            toml::Value::Integer(item) => Some(item.to_string()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    if items.is_none() {
+                        return Err(format!("Unsupported answer value for key '{}'", key));
+                    }
+                    TemplateRender::join_list(&items.unwrap())
+                }
+                _ => return Err(format!("Unsupported answer value for key '{}'", key)),
+            };
+            values.push((key, value));
+        }
+        Ok(values)
+    }
+
+    pub fn insert(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let key = TemplateAnswers::normalize_key(key)?;
+        self.values.insert(key, value.to_string());
+        Ok(())
+    }
+
+    /**
+     * Later answers win, so `--set` flags can override values from answers file.
+     */
+    pub fn merge(&mut self, answers: &TemplateAnswers) {
+        for (key, value) in answers.values.iter() {
+            self.values.insert(key.to_owned(), value.to_owned());
+        }
+    }
+
+    pub fn get(&self, variable: &TemplateVariableInfo) -> Option<&String> {
+        let key = SearchFolder::get_key(variable);
+        if let Some(value) = self.values.get(&key) {
+            return Some(value);
+        }
+        if variable.var_name.is_empty() {
+            return None;
+        }
+        self.values.get(&variable.var_name)
+    }
+
+    /**
+     * Key hint which can be used with --set flag, for example __var__name or __select__
+     */
+    pub fn key_hint(variable: &TemplateVariableInfo) -> String {
+        format!("{}{}", variable.template_variable, variable.var_name)
+    }
+
+    fn normalize_key(key: &str) -> Result<String, String> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err("Answer key cannot be empty".to_string());
+        }
+        if !key.starts_with("__") {
+            return Ok(key.to_string());
+        }
+
+        let token = if key.ends_with("__") {
+            key.to_string()
+        } else {
+            format!("{}__", key)
+        };
+        let variable = TemplateVariableInfo::from_str(&token);
+        match variable {
+            Some(variable)
+                if variable.start_index == 0
+                    && variable.raw_value == token
+                    && variable.template_variable.is_value() =>
+            {
+                // key names variable, `__var__title` is variable `title`, not title case
+                Ok(SearchFolder::get_key(&variable.as_named()))
+            }
+            _ => Err(format!("Unknown template variable '{}'", key)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateAnswers;
+    use crate::{template_render::TemplateRender, template_variable::TemplateVariableInfo};
+
+    #[test]
+    fn should_parse_set_arguments() {
+        let arguments = vec![
+            "__var__name=Button".to_string(),
+            "__select__kind__=basic".to_string(),
+            "__var__=Main".to_string(),
+            "author=Jane=Doe".to_string(),
+            "__var__title=Home".to_string(),
+        ];
+        let answers = TemplateAnswers::from_set_arguments(&arguments).unwrap();
+
+        let variable = TemplateVariableInfo::from_str("__var__name__kebab__").unwrap();
+        assert_eq!(answers.get(&variable).unwrap(), "Button");
+
+        let variable = TemplateVariableInfo::from_str("__select__kind__").unwrap();
+        assert_eq!(answers.get(&variable).unwrap(), "basic");
+
+        let variable = TemplateVariableInfo::from_str("__var__").unwrap();
+        assert_eq!(answers.get(&variable).unwrap(), "Main");
+
+        let variable = TemplateVariableInfo::from_str("__select__author__").unwrap();
+        assert_eq!(answers.get(&variable).unwrap(), "Jane=Doe");
+
+        let variable = TemplateVariableInfo::from_str("__var__title__kebab__").unwrap();
+        assert_eq!(answers.get(&variable).unwrap(), "Home");
+
+        let variable = TemplateVariableInfo::from_str("__select__").unwrap();
+        assert!(answers.get(&variable).is_none());
+    }
+
+    #[test]
+    fn should_fail_on_invalid_set_arguments() {
+        let result = TemplateAnswers::from_set_arguments(&["__var__name".to_string()]);
+        assert!(result.is_err());
+
+        let result = TemplateAnswers::from_set_arguments(&["__nope__name=1".to_string()]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_parse_answers_content() {
+        let json = r#"{ "__var__name": "Button", "__select__kind": "basic", "count": 3 }"#;
+        let answers = TemplateAnswers::from_content(json, Some("json")).unwrap();
+        assert_eq!(answers.values.len(), 3);
+        assert_eq!(answers.values.get("count").unwrap(), "3");
+
+        let toml = "__var__name = \"Button\"\n__select__kind = \"basic\"\n";
+        let answers = TemplateAnswers::from_content(toml, None).unwrap();
+        let variable = TemplateVariableInfo::from_str("__select__kind__").unwrap();
+        assert_eq!(answers.get(&variable).unwrap(), "basic");
+
+        let result = TemplateAnswers::from_content("[1, 2]", Some("json"));
+        assert!(result.is_err());
+
+        let json = r#"{ "__list__fields": ["id", "name", 3] }"#;
+        let answers = TemplateAnswers::from_content(json, Some("json")).unwrap();
+        assert_eq!(
+            answers.values.get("__list___fields").unwrap(),
+            "id, name, 3"
+        );
+
+        let toml = "__list__fields = [\"id\", \"name\"]\n";
+        let answers = TemplateAnswers::from_content(toml, Some("toml")).unwrap();
+        assert_eq!(answers.values.get("__list___fields").unwrap(), "id, name");
+
+        // comma inside of array item is escaped, so it stays one item
+        let json = r#"{ "__list__items": ["a, b", "c\\d"] }"#;
+        let answers = TemplateAnswers::from_content(json, Some("json")).unwrap();
+        let value = answers.values.get("__list___items").unwrap();
+        assert_eq!(value, "a\\, b, c\\\\d");
+        assert_eq!(TemplateRender::split_list(value), ["a, b", "c\\d"]);
+    }
+}
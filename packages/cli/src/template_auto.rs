@@ -0,0 +1,197 @@
+use crate::{
+    search_folder::SearchFolder, template::TemplateConfig, template_answers::TemplateAnswers,
+    template_render::TemplateRender, template_variable::TemplateVariableInfo,
+};
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/**
+ * Built-in values which can be used by `auto__` variables.
+ * Variable named by context value (`__var__date__auto__`) gets the value directly,
+ * expressions in template `_.json` can use them as `{date}`, `{dir}`, etc...
+ */
+#[derive(Debug, Clone)]
+pub struct TemplateAutoContext {
+    pub values: HashMap<String, String>,
+}
+
+impl TemplateAutoContext {
+    pub fn new(cwd: &Path, template_name: &str) -> TemplateAutoContext {
+        let mut values = HashMap::new();
+        let dir = cwd
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        values.insert("dir".to_string(), dir);
+        values.insert("template".to_string(), template_name.to_string());
+
+        let (year, month, day) = TemplateAutoContext::today();
+        values.insert(
+            "date".to_string(),
+            format!("{:04}-{:02}-{:02}", year, month, day),
+        );
+        values.insert("year".to_string(), format!("{:04}", year));
+
+        if let Ok(git_config) = git2::Config::open_default() {
+            if let Ok(user) = git_config.get_string("user.name") {
+                values.insert("git_user".to_string(), user);
+            }
+            if let Ok(email) = git_config.get_string("user.email") {
+                values.insert("git_email".to_string(), email);
+            }
+        }
+
+        TemplateAutoContext { values }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        let name = match name {
+            "cwd" | "folder" => "dir",
+            "author" | "gituser" => "git_user",
+            "email" | "gitemail" => "git_email",
+            name => name,
+        };
+        self.values.get(name)
+    }
+
+    fn today() -> (i64, u32, u32) {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        TemplateAutoContext::civil_from_days(seconds / 86400)
+    }
+
+    /**
+     * Convert days since unix epoch to (year, month, day).
+     * http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+     */
+    fn civil_from_days(days: i64) -> (i64, u32, u32) {
+        let days = days + 719468;
+        let era = if days >= 0 { days } else { days - 146096 } / 146097;
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_part = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_part + 2) / 5 + 1) as u32;
+        let month = if month_part < 10 {
+            month_part + 3
+        } else {
+            month_part - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+}
+
+pub struct TemplateAuto;
+
+impl TemplateAuto {
+    /**
+     * Compute value of `auto__` variable. Expression from template `_.json` `auto_values` wins,
+     * then built-in context value with the same name as variable.
+     * Returns None when value cannot be computed yet (expression depends on unresolved variable).
+     */
+    pub fn resolve(
+        variable: &TemplateVariableInfo,
+        template_config: &TemplateConfig,
+        context: &TemplateAutoContext,
+        values_for_keys: &HashMap<String, String>,
+    ) -> Option<String> {
+        if let Some(expression) = TemplateAuto::get_expression(variable, template_config) {
+            return TemplateAuto::evaluate(expression, context, values_for_keys);
+        }
+        context.get(&variable.var_name).cloned()
+    }
+
+    fn get_expression<'a>(
+        variable: &TemplateVariableInfo,
+        template_config: &'a TemplateConfig,
+    ) -> Option<&'a String> {
+        let auto_values = template_config.auto_values.as_ref()?;
+        auto_values
+            .get(&TemplateAnswers::key_hint(variable))
+            .or_else(|| auto_values.get(&variable.raw_value))
+            .or_else(|| auto_values.get(&variable.var_name))
+    }
+
+    /**
+     * Expression is a text with template variables (with optional case suffix)
+     * and `{name}` context placeholders, for example `__var__name__kebab__-{date}`.
+     */
+    pub fn evaluate(
+        expression: &str,
+        context: &TemplateAutoContext,
+        values_for_keys: &HashMap<String, String>,
+    ) -> Option<String> {
+        let mut result = String::new();
+        let mut last_index = 0;
+        for variable in TemplateVariableInfo::parse_iter(expression) {
+            result.push_str(&expression[last_index..variable.start_index]);
+            let variable =
+                SearchFolder::resolve_name(variable, |key| values_for_keys.contains_key(key));
+            let value = values_for_keys.get(&SearchFolder::get_key(&variable))?;
+            let value = match variable.template_variable.is_list() {
+                true => TemplateRender::display_list(&TemplateRender::split_list(value)),
+                false => value.to_owned(),
+            };
+            result.push_str(&variable.case_type.to_case_string(&value));
+            last_index = variable.end_index;
+        }
+        result.push_str(&expression[last_index..]);
+
+        let mut output = String::new();
+        let mut rest = result.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}');
+            if end.is_none() {
+                break;
+            }
+            let end = start + end.unwrap();
+            let name = &rest[start + 1..end];
+            output.push_str(&rest[..start]);
+            match context.get(name) {
+                Some(value) => output.push_str(value),
+                None => output.push_str(&rest[start..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        output.push_str(rest);
+        Some(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplateAuto, TemplateAutoContext};
+    use std::{collections::HashMap, path::Path};
+
+    #[test]
+    fn should_convert_days_to_date() {
+        assert_eq!(TemplateAutoContext::civil_from_days(0), (1970, 1, 1));
+        assert_eq!(TemplateAutoContext::civil_from_days(19782), (2024, 2, 29));
+        assert_eq!(TemplateAutoContext::civil_from_days(20744), (2026, 10, 18));
+    }
+
+    #[test]
+    fn should_evaluate_expression() {
+        let context = TemplateAutoContext::new(Path::new("/projects/my-app"), "component");
+        let mut values = HashMap::new();
+        values.insert("__var___name".to_string(), "my button".to_string());
+
+        let result = TemplateAuto::evaluate(
+            "__var__name__kebab__/{dir}/{template}/{unknown}",
+            &context,
+            &values,
+        );
+        assert_eq!(result.unwrap(), "my-button/my-app/component/{unknown}");
+
+        let result = TemplateAuto::evaluate("__var__other__", &context, &values);
+        assert!(result.is_none());
+    }
+}
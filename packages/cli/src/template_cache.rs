@@ -0,0 +1,220 @@
+use crate::{
+    config::Config,
+    constants::TEMPLATE_CACHE_FOLDER,
+    template::TemplateFolder,
+    template_git::TemplateGit,
+    template_source::{FetchedSource, TemplateSource},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// file in cached source folder with commit used last time, read in offline mode
+const CACHE_HEAD_FILE: &str = "HEAD";
+
+/**
+ * Remote templates used without fetching them to `.gen`, e.g. `gen use git+file:///repo#component`.
+ * Sources are checked out to `~/.gen/.cache/<source>/<commit>`, so repeated runs only ask
+ * the remote for its current commit, and `--offline` runs use last cached commit.
+ */
+pub struct TemplateCache;
+
+impl TemplateCache {
+    /**
+     * Template names never contain `:`, so urls & scp like `git@host:repo` are remote templates.
+     */
+    pub fn is_remote(template: &str) -> bool {
+        template.starts_with("git+") || template.contains(':')
+    }
+
+    pub fn resolve(template: &str, offline: bool) -> Result<TemplateFolder, String> {
+        let cache_path = Config::global_path().join(TEMPLATE_CACHE_FOLDER);
+        TemplateCache::resolve_in(&cache_path, template, offline)
+    }
+
+    fn resolve_in(
+        cache_path: &Path,
+        template: &str,
+        offline: bool,
+    ) -> Result<TemplateFolder, String> {
+        let source = template.strip_prefix("git+").unwrap_or(template);
+        let (source, template_name) = TemplateSource::split_subdir(source);
+        if let Some(template_name) = template_name {
+            TemplateSource::validate_subdir(template_name)?;
+        }
+        let template_source = TemplateSource::parse(source);
+        let TemplateSource::Git(url) = &template_source else {
+            return Err(format!(
+                "Only git repositories can be used without fetching, use gen fetch {}",
+                source
+            ));
+        };
+
+        let source_path = cache_path.join(TemplateCache::source_key(&template_source, url));
+        let commit = if offline {
+            fs::read_to_string(source_path.join(CACHE_HEAD_FILE))
+                .map(|commit| commit.trim().to_string())
+                .map_err(|_| format!("{} is not cached, run it without --offline first", url))?
+        } else {
+            TemplateCache::fetch(&source_path, url)?
+        };
+        let commit_path = source_path.join(&commit);
+        if !commit_path.is_dir() {
+            return Err(format!(
+                "{} is not cached, run it without --offline first",
+                url
+            ));
+        }
+
+        let fetched_source = FetchedSource {
+            root: commit_path,
+            commit: Some(commit),
+            name: template_source.name(),
+        };
+        let template_folder = TemplateCache::find_template(&fetched_source, template_name)?;
+        Ok(TemplateFolder {
+            name: template.to_string(),
+            path: template_folder.path,
+        })
+    }
+
+    /**
+     * Checkout of current remote commit, cloned only when it is not cached yet.
+     */
+    fn fetch(source_path: &Path, url: &str) -> Result<String, String> {
+        let commit = TemplateGit::remote_commit(url)
+            .map_err(|error| format!("{}, use --offline to use cached version", error))?
+            .to_string();
+        fs::create_dir_all(source_path)
+            .map_err(|error| format!("Cannot create {}: {}", source_path.display(), error))?;
+        if !source_path.join(&commit).is_dir() {
+            // cloned next to final folder, so it can be renamed when clone succeeds
+            let clone_path = source_path.join(format!(".clone-{}", process::id()));
+            let _ = fs::remove_dir_all(&clone_path);
+            let result = TemplateGit::clone(url, Some(&commit), &clone_path)
+                .or_else(|_| {
+                    // servers which do not allow fetching by sha, remote could move meanwhile
+                    let _ = fs::remove_dir_all(&clone_path);
+                    TemplateGit::clone(url, None, &clone_path)
+                })
+                .and_then(|cloned_commit| {
+                    let _ = fs::remove_dir_all(clone_path.join(".git"));
+                    let destination = source_path.join(cloned_commit.to_string());
+                    if !destination.exists() {
+                        fs::rename(&clone_path, &destination).map_err(|error| {
+                            format!("Cannot cache {}: {}", destination.display(), error)
+                        })?;
+                    }
+                    Ok(cloned_commit.to_string())
+                });
+            let _ = fs::remove_dir_all(&clone_path);
+            let cloned_commit = result?;
+            return TemplateCache::save_head(source_path, &cloned_commit);
+        }
+        TemplateCache::save_head(source_path, &commit)
+    }
+
+    fn save_head(source_path: &Path, commit: &str) -> Result<String, String> {
+        fs::write(source_path.join(CACHE_HEAD_FILE), commit)
+            .map_err(|error| format!("Cannot write {}: {}", source_path.display(), error))?;
+        Ok(commit.to_string())
+    }
+
+    /**
+     * Template by name, or by path in the source. Source with single template can skip it.
+     */
+    fn find_template(
+        fetched_source: &FetchedSource,
+        template_name: Option<&str>,
+    ) -> Result<TemplateFolder, String> {
+        let templates = fetched_source.templates(None).unwrap_or_default();
+        let Some(template_name) = template_name else {
+            return match templates.as_slice() {
+                [template_folder] => Ok(template_folder.to_owned()),
+                _ => Err(format!(
+                    "Select template with source#template, available templates: {}",
+                    TemplateCache::names(&templates)
+                )),
+            };
+        };
+        if let Some(template_folder) = templates.iter().find(|item| item.name == template_name) {
+            return Ok(template_folder.to_owned());
+        }
+        match fetched_source.templates(Some(template_name)).as_deref() {
+            Ok([template_folder]) => Ok(template_folder.to_owned()),
+            _ => Err(format!(
+                "Template {} not found, available templates: {}",
+                template_name,
+                TemplateCache::names(&templates)
+            )),
+        }
+    }
+
+    fn names(templates: &[TemplateFolder]) -> String {
+        templates
+            .iter()
+            .map(|template_folder| template_folder.name.to_owned())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /**
+     * Readable and unique folder name of the source, e.g. `templates-1f2e3d4c5b6a`.
+     */
+    fn source_key(template_source: &TemplateSource, url: &str) -> PathBuf {
+        let hash = Sha256::digest(url.as_bytes())
+            .iter()
+            .take(6)
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let name = template_source.name().replace(char::is_whitespace, "-");
+        PathBuf::from(format!("{}-{}", name, hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateCache;
+    use crate::template_git::{tests::create_bare_repository, TemplateGit};
+    use std::fs;
+
+    #[test]
+    fn should_use_cached_remote_template() {
+        let path = TemplateGit::temp_directory("test-cache").unwrap();
+        let (_, second) = create_bare_repository(&path);
+        let cache_path = path.join("cache");
+        let template = format!("git+file://{}#component", path.join("bare.git").display());
+        assert!(TemplateCache::is_remote(&template));
+        assert!(TemplateCache::is_remote(
+            "git@github.com:acme/templates#component"
+        ));
+        assert!(!TemplateCache::is_remote("acme/component"));
+
+        let result = TemplateCache::resolve_in(&cache_path, &template, true);
+        assert!(result.unwrap_err().contains("is not cached"));
+        let outside = format!("git+file://{}#../..", path.join("bare.git").display());
+        let result = TemplateCache::resolve_in(&cache_path, &outside, false);
+        assert!(result.unwrap_err().contains("Invalid subdirectory"));
+
+        let template_folder = TemplateCache::resolve_in(&cache_path, &template, false).unwrap();
+        assert_eq!(template_folder.name, template);
+        assert!(template_folder.path.starts_with(&cache_path));
+        assert!(template_folder
+            .path
+            .to_string_lossy()
+            .contains(&second.to_string()));
+        let content = fs::read_to_string(template_folder.path.join("__var__.ts")).unwrap();
+        assert_eq!(content, "second");
+
+        // remote is not needed for cached commit
+        fs::remove_dir_all(path.join("bare.git")).unwrap();
+        let offline_folder = TemplateCache::resolve_in(&cache_path, &template, true).unwrap();
+        assert_eq!(offline_folder.path, template_folder.path);
+        assert!(TemplateCache::resolve_in(&cache_path, &template, false).is_err());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    case_util::CaseType,
+    config::ConfigFile,
+    template::{TemplateCaseType, TemplateConfig},
+    template_answers::TemplateAnswers,
+    template_variable::TemplateVariableInfo,
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+/**
+ * Level which decided case type of variable, from the most specific one.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateCaseSource {
+    Inline,
+    Variable,
+    Template,
+    Project,
+    Global,
+    Default,
+}
+
+impl Display for TemplateCaseSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateCaseSource::Inline => write!(f, "inline case suffix"),
+            TemplateCaseSource::Variable => write!(f, "variable entry in template _.json"),
+            TemplateCaseSource::Template => write!(f, "template _.json case_type"),
+            TemplateCaseSource::Project => write!(f, "project .gen/_.json"),
+            TemplateCaseSource::Global => write!(f, "global ~/.gen/_.json"),
+            TemplateCaseSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/**
+ * Resolve case type of variable with precedence:
+ * inline case suffix, per-variable entry in template `_.json`, template `case_type`,
+ * project `.gen/_.json`, global `~/.gen/_.json` and default.
+ * Unknown case type on any level means it's not set there.
+ */
+#[derive(Debug, Clone)]
+pub struct TemplateCaseResolver {
+    variable_case_types: HashMap<String, TemplateCaseType>,
+    levels: Vec<(TemplateCaseSource, TemplateCaseType)>,
+}
+
+impl TemplateCaseResolver {
+    pub fn new(
+        template_config: &TemplateConfig,
+        project_config: Option<&ConfigFile>,
+        global_config: &ConfigFile,
+    ) -> TemplateCaseResolver {
+        let mut levels = Vec::new();
+        if let Some(case_type) = &template_config.case_type {
+            levels.push((TemplateCaseSource::Template, case_type.to_owned()));
+        }
+        if let Some(case_type) = project_config.and_then(|config| config.case_type.as_ref()) {
+            levels.push((TemplateCaseSource::Project, case_type.to_owned()));
+        }
+        if let Some(case_type) = &global_config.case_type {
+            levels.push((TemplateCaseSource::Global, case_type.to_owned()));
+        }
+        levels.push((TemplateCaseSource::Default, TemplateCaseType::new()));
+
+        TemplateCaseResolver {
+            variable_case_types: template_config
+                .variable_case_types
+                .to_owned()
+                .unwrap_or_default(),
+            levels,
+        }
+    }
+
+    pub fn resolve(
+        &self,
+        variable: &TemplateVariableInfo,
+        is_file_path: bool,
+    ) -> (CaseType, TemplateCaseSource) {
+        if variable.case_type.is_not_unknown() {
+            return (variable.case_type.to_owned(), TemplateCaseSource::Inline);
+        }
+
+        let variable_case_type = self
+            .variable_case_types
+            .get(&TemplateAnswers::key_hint(variable))
+            .or_else(|| self.variable_case_types.get(&variable.var_name));
+        let variable_level =
+            variable_case_type.map(|case_type| (TemplateCaseSource::Variable, case_type));
+        let levels = self
+            .levels
+            .iter()
+            .map(|(source, case_type)| (*source, case_type));
+
+        for (source, case_type) in variable_level.into_iter().chain(levels) {
+            let case_type = if is_file_path {
+                &case_type.file
+            } else {
+                &case_type.content
+            };
+            if case_type.is_not_unknown() {
+                return (case_type.to_owned(), source);
+            }
+        }
+        (CaseType::Unknown, TemplateCaseSource::Default)
+    }
+
+    pub fn to_case(
+        &self,
+        variable: &TemplateVariableInfo,
+        is_file_path: bool,
+        value: &str,
+    ) -> String {
+        let (case_type, _) = self.resolve(variable, is_file_path);
+        case_type.to_case_string(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplateCaseResolver, TemplateCaseSource};
+    use crate::{
+        case_util::CaseType,
+        config::ConfigFile,
+        template::{TemplateCaseType, TemplateConfig},
+        template_variable::TemplateVariableInfo,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn should_resolve_case_by_precedence() {
+        let mut global_config = ConfigFile::new();
+        global_config.case_type = Some(TemplateCaseType {
+            content: CaseType::CamelCase,
+            file: CaseType::SnakeCase,
+        });
+        let mut project_config = ConfigFile::new();
+        project_config.case_type = Some(TemplateCaseType {
+            content: CaseType::Unknown,
+            file: CaseType::KebabCase,
+        });
+        let mut template_config = TemplateConfig::new();
+
+        let variable = TemplateVariableInfo::from_str("__var__name__").unwrap();
+        let resolver =
+            TemplateCaseResolver::new(&template_config, Some(&project_config), &global_config);
+        assert_eq!(
+            resolver.resolve(&variable, true),
+            (CaseType::KebabCase, TemplateCaseSource::Project)
+        );
+        assert_eq!(
+            resolver.resolve(&variable, false),
+            (CaseType::CamelCase, TemplateCaseSource::Global)
+        );
+
+        template_config.case_type = Some(TemplateCaseType {
+            content: CaseType::SnakeCase,
+            file: CaseType::Unknown,
+        });
+        let mut variable_case_types = HashMap::new();
+        variable_case_types.insert(
+            "__var__name".to_string(),
+            TemplateCaseType {
+                content: CaseType::Unknown,
+                file: CaseType::PascalCase,
+            },
+        );
+        template_config.variable_case_types = Some(variable_case_types);
+        let resolver =
+            TemplateCaseResolver::new(&template_config, Some(&project_config), &global_config);
+        assert_eq!(
+            resolver.resolve(&variable, true),
+            (CaseType::PascalCase, TemplateCaseSource::Variable)
+        );
+        assert_eq!(
+            resolver.resolve(&variable, false),
+            (CaseType::SnakeCase, TemplateCaseSource::Template)
+        );
+
+        let variable = TemplateVariableInfo::from_str("__var__name__kebab__").unwrap();
+        assert_eq!(
+            resolver.resolve(&variable, false),
+            (CaseType::KebabCase, TemplateCaseSource::Inline)
+        );
+
+        let resolver = TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        let variable = TemplateVariableInfo::from_str("__var__other__").unwrap();
+        assert_eq!(
+            resolver.resolve(&variable, false),
+            (CaseType::PascalCase, TemplateCaseSource::Default)
+        );
+        assert_eq!(resolver.to_case(&variable, true, "My Button"), "my-button");
+    }
+}
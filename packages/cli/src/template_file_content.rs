@@ -7,10 +7,13 @@ pub const TEMPLATE_FILE_CONTENT: &str = r#"
 ### Using variables:
  1. Basic -> __var__
  2. Basic with custom naming -> __var__some
- 4. Variables also supports case type prefix -> __var__kebab__, __var__pascal__, __var__camel__, __var__snake__ or __var__my_name_snake
+ 4. Variables also supports case type prefix -> __var__kebab__, __var__pascal__, __var__camel__, __var__snake__, __var__screaming__, __var__title__, __var__dot__, __var__path__, __var__flat__, __var__train__, __var__lower__, __var__upper__ or __var__my_name_snake
  5. For dropdowns selections -> #select
  6. It's same as __var__, expect that template folder contain config json which should contains array of available values.
  7. Same variable rules is applied for template paths as well.
+ 8. Yes / no question -> __bool__name__
+ 9. Conditional blocks -> __if__select__kind__complex__ ... __elif__bool__name__ ... __else__ ... __endif__
+ 10. List of values -> __list__name__, repeated with __each__name__ ... __endeach__
 
 #### Example rust:
 ```rust
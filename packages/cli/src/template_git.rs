@@ -0,0 +1,446 @@
+use crate::{constants::TEMPLATE_ROOT_FOLDER, template_lock::TemplateLock};
+use git2::{
+    build::{CheckoutBuilder, RepoBuilder},
+    AutotagOption, Cred, CredentialType, Direction, FetchOptions, IndexAddOption, Oid, PushOptions,
+    RemoteCallbacks, Repository, Signature,
+};
+use std::{
+    env::temp_dir,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/**
+ * Git operations used by fetch & publish, built on libgit2 so no `git` binary is needed.
+ */
+pub struct TemplateGit;
+
+impl TemplateGit {
+    /**
+     * New empty directory in system temp dir, unique per run so parallel fetches do not collide.
+     */
+    pub fn temp_directory(prefix: &str) -> Result<PathBuf, String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = temp_dir().join(format!("gen-{}-{}-{}", prefix, process::id(), nanos));
+        fs::create_dir_all(&path)
+            .map_err(|error| format!("Cannot create temp dir {}: {}", path.display(), error))?;
+        Ok(path)
+    }
+
+    /**
+     * Shallow clone of `url` into `destination`, `git_ref` can be branch, tag or commit sha,
+     * default branch is used without it. Returns checked out commit.
+     */
+    pub fn clone(url: &str, git_ref: Option<&str>, destination: &Path) -> Result<Oid, String> {
+        let repository = Repository::init(destination).map_err(|error| {
+            format!(
+                "Cannot create repository in {}: {}",
+                destination.display(),
+                error.message()
+            )
+        })?;
+        let mut remote = repository
+            .remote_anonymous(url)
+            .map_err(|error| format!("Invalid repository url {}: {}", url, error.message()))?;
+
+        let refspecs = match git_ref {
+            Some(git_ref) => vec![
+                format!("+refs/heads/{0}:refs/remotes/origin/{0}", git_ref),
+                format!("+refs/tags/{0}:refs/tags/{0}", git_ref),
+                git_ref.to_string(),
+            ],
+            None => vec!["HEAD".to_string()],
+        };
+
+        let mut last_error = None;
+        let mut is_fetched = false;
+        for refspec in refspecs.iter() {
+            // sha can be fetched only when it is a full object id
+            if git_ref.is_some_and(|git_ref| refspec == git_ref) && Oid::from_str(refspec).is_err()
+            {
+                continue;
+            }
+            let result = TemplateGit::fetch(&mut remote, refspec);
+            match result {
+                Ok(_) if repository.find_reference("FETCH_HEAD").is_ok() => {
+                    is_fetched = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        if !is_fetched {
+            return Err(match (git_ref, last_error) {
+                (Some(git_ref), Some(error)) if TemplateGit::is_connection_error(&error) => {
+                    format!("Cannot fetch {} from {}: {}", git_ref, url, error.message())
+                }
+                (Some(git_ref), _) => format!("Reference '{}' not found in {}", git_ref, url),
+                (None, Some(error)) => format!("Cannot fetch {}: {}", url, error.message()),
+                (None, None) => format!("Repository {} is empty", url),
+            });
+        }
+
+        let commit = repository
+            .find_reference("FETCH_HEAD")
+            .and_then(|reference| reference.peel_to_commit())
+            .map_err(|error| format!("Cannot read fetched commit: {}", error.message()))?;
+        repository
+            .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))
+            .and_then(|_| repository.set_head_detached(commit.id()))
+            .map_err(|error| format!("Cannot checkout {}: {}", commit.id(), error.message()))?;
+        Ok(commit.id())
+    }
+
+    /**
+     * Commit of remote default branch, without downloading anything (same as `git ls-remote`).
+     */
+    pub fn remote_commit(url: &str) -> Result<Oid, String> {
+        let mut remote = git2::Remote::create_detached(url)
+            .map_err(|error| format!("Invalid repository url {}: {}", url, error.message()))?;
+        remote
+            .connect_auth(
+                Direction::Fetch,
+                Some(TemplateGit::remote_callbacks()),
+                None,
+            )
+            .map_err(|error| format!("Cannot connect to {}: {}", url, error.message()))?;
+        let commit = remote
+            .list()
+            .map_err(|error| format!("Cannot list {}: {}", url, error.message()))?
+            .iter()
+            .find(|head| head.name() == "HEAD")
+            .map(|head| head.oid());
+        let _ = remote.disconnect();
+        commit.ok_or_else(|| format!("Repository {} is empty", url))
+    }
+
+    /**
+     * Clone `url`, let `change` modify template folder `.gen/<template_name>` in the working copy,
+     * then commit it with `message` and push to the default branch. Remote can be empty.
+     * Returns None when nothing changed, so nothing was pushed.
+     */
+    pub fn commit_and_push<F>(
+        url: &str,
+        template_name: &str,
+        message: &str,
+        change: F,
+    ) -> Result<Option<Oid>, String>
+    where
+        F: FnOnce(&Path) -> Result<(), String>,
+    {
+        let clone_path = TemplateGit::temp_directory("publish")?;
+        let result =
+            TemplateGit::commit_and_push_in(url, template_name, message, change, &clone_path);
+        let _ = fs::remove_dir_all(&clone_path);
+        result
+    }
+
+    fn commit_and_push_in<F>(
+        url: &str,
+        template_name: &str,
+        message: &str,
+        change: F,
+        clone_path: &Path,
+    ) -> Result<Option<Oid>, String>
+    where
+        F: FnOnce(&Path) -> Result<(), String>,
+    {
+        // full clone, pushing commit on top of shallow history is not supported by libgit2
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(TemplateGit::remote_callbacks());
+        let repository = RepoBuilder::new()
+            .fetch_options(fetch_options)
+            .clone(url, clone_path)
+            .map_err(|error| format!("Cannot clone {}: {}", url, error.message()))?;
+
+        let template_path = Path::new(TEMPLATE_ROOT_FOLDER).join(template_name);
+        change(&clone_path.join(&template_path))?;
+
+        let git_error = |error: git2::Error| format!("Cannot commit: {}", error.message());
+        let mut index = repository.index().map_err(git_error)?;
+        let pathspec = TemplateLock::relative_path(Path::new(""), &template_path);
+        index
+            .add_all([&pathspec].iter(), IndexAddOption::DEFAULT, None)
+            .and_then(|_| index.update_all([&pathspec].iter(), None))
+            .and_then(|_| index.write())
+            .map_err(git_error)?;
+        let tree_id = index.write_tree().map_err(git_error)?;
+
+        // empty remote has unborn HEAD, commit is created without parent
+        let parent = repository
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        if parent
+            .as_ref()
+            .is_some_and(|parent| parent.tree_id() == tree_id)
+        {
+            return Ok(None);
+        }
+        let branch = repository
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(|target| target.to_string()))
+            .unwrap_or_else(|| "refs/heads/main".to_string());
+
+        let tree = repository.find_tree(tree_id).map_err(git_error)?;
+        let signature = repository
+            .signature()
+            .or_else(|_| Signature::now("gen", "gen@localhost"))
+            .map_err(git_error)?;
+        let parents = parent.iter().collect::<Vec<_>>();
+        let commit = repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .map_err(git_error)?;
+
+        TemplateGit::push(&repository, url, &branch)?;
+        Ok(Some(commit))
+    }
+
+    fn push(repository: &Repository, url: &str, branch: &str) -> Result<(), String> {
+        let mut remote = repository
+            .find_remote("origin")
+            .map_err(|error| format!("Cannot push to {}: {}", url, error.message()))?;
+        let mut rejection = None;
+        let mut callbacks = TemplateGit::remote_callbacks();
+        callbacks.push_update_reference(|_, status| {
+            rejection = status.map(|status| status.to_string());
+            Ok(())
+        });
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+        remote
+            .push(&[format!("{0}:{0}", branch)], Some(&mut push_options))
+            .map_err(|error| format!("Cannot push to {}: {}", url, error.message()))?;
+        drop(push_options);
+        match rejection {
+            Some(rejection) => Err(format!("Push to {} was rejected: {}", url, rejection)),
+            None => Ok(()),
+        }
+    }
+
+    fn fetch(remote: &mut git2::Remote, refspec: &str) -> Result<(), git2::Error> {
+        let mut fetch_options = FetchOptions::new();
+        fetch_options
+            .remote_callbacks(TemplateGit::remote_callbacks())
+            .download_tags(AutotagOption::None)
+            .depth(1);
+        remote.fetch(&[refspec], Some(&mut fetch_options), None)
+    }
+
+    fn is_connection_error(error: &git2::Error) -> bool {
+        matches!(
+            error.class(),
+            git2::ErrorClass::Net
+                | git2::ErrorClass::Http
+                | git2::ErrorClass::Ssh
+                | git2::ErrorClass::Ssl
+                | git2::ErrorClass::Os
+        ) || error.code() == git2::ErrorCode::Auth
+    }
+
+    /**
+     * Credentials from ssh-agent for ssh urls and from git credential helper for https.
+     */
+    pub fn remote_callbacks<'a>() -> RemoteCallbacks<'a> {
+        let mut callbacks = RemoteCallbacks::new();
+        let mut attempts = 0;
+        callbacks.credentials(move |url, username, allowed_types| {
+            attempts += 1;
+            if attempts > 3 {
+                return Err(git2::Error::from_str("authentication failed"));
+            }
+            if allowed_types.contains(CredentialType::SSH_KEY) {
+                return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+            }
+            if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                let config = git2::Config::open_default()?;
+                return Cred::credential_helper(&config, url, username);
+            }
+            Cred::default()
+        });
+        callbacks
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::TemplateGit;
+    use git2::{Oid, Repository, Signature};
+    use std::{fs, path::Path};
+
+    /**
+     * Bare repository with two commits on main, tag v1 on first one.
+     */
+    pub fn create_bare_repository(path: &Path) -> (Oid, Oid) {
+        let work_path = path.join("work");
+        let repository = Repository::init(&work_path).unwrap();
+        let signature = Signature::now("gen", "gen@example.com").unwrap();
+
+        let commit = |content: &str, parents: &[&git2::Commit]| {
+            fs::create_dir_all(work_path.join(".gen/component")).unwrap();
+            fs::write(work_path.join(".gen/component/__var__.ts"), content).unwrap();
+            let mut index = repository.index().unwrap();
+            index
+                .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+                .unwrap();
+            index.write().unwrap();
+            let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+            repository
+                .commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    content,
+                    &tree,
+                    parents,
+                )
+                .unwrap()
+        };
+        let first = commit("first", &[]);
+        let first_commit = repository.find_commit(first).unwrap();
+        let second = commit("second", &[&first_commit]);
+        repository
+            .tag_lightweight("v1", first_commit.as_object(), false)
+            .unwrap();
+
+        let bare_path = path.join("bare.git");
+        let bare = Repository::init_bare(&bare_path).unwrap();
+        let mut remote = repository
+            .remote_anonymous(bare_path.to_str().unwrap())
+            .unwrap();
+        let head = repository.head().unwrap();
+        let branch = head.name().unwrap();
+        remote
+            .push(
+                &[
+                    format!("{0}:refs/heads/main", branch),
+                    "refs/tags/v1:refs/tags/v1".to_string(),
+                ],
+                None,
+            )
+            .unwrap();
+        bare.set_head("refs/heads/main").unwrap();
+        (first, second)
+    }
+
+    #[test]
+    fn should_clone_local_repository() {
+        let path = TemplateGit::temp_directory("test-clone").unwrap();
+        let (first, second) = create_bare_repository(&path);
+        let url = format!("file://{}", path.join("bare.git").display());
+
+        let destination = path.join("default");
+        let commit = TemplateGit::clone(&url, None, &destination).unwrap();
+        assert_eq!(commit, second);
+        let content = fs::read_to_string(destination.join(".gen/component/__var__.ts")).unwrap();
+        assert_eq!(content, "second");
+
+        let destination = path.join("tag");
+        let commit = TemplateGit::clone(&url, Some("v1"), &destination).unwrap();
+        assert_eq!(commit, first);
+
+        let destination = path.join("branch");
+        let bare_path = path.join("bare.git");
+        let commit =
+            TemplateGit::clone(bare_path.to_str().unwrap(), Some("main"), &destination).unwrap();
+        assert_eq!(commit, second);
+
+        let destination = path.join("sha");
+        let commit = TemplateGit::clone(&url, Some(&first.to_string()), &destination).unwrap();
+        assert_eq!(commit, first);
+
+        let result = TemplateGit::clone(&url, Some("missing"), &path.join("missing"));
+        assert_eq!(
+            result.unwrap_err(),
+            format!("Reference 'missing' not found in {}", url)
+        );
+
+        let result = TemplateGit::clone(
+            &format!("file://{}", path.join("nope").display()),
+            None,
+            &path.join("nope-clone"),
+        );
+        assert!(result.is_err());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn should_publish_and_unpublish_template() {
+        let path = TemplateGit::temp_directory("test-publish").unwrap();
+        let (_, second) = create_bare_repository(&path);
+        let url = path.join("bare.git").to_str().unwrap().to_string();
+        let publish = |content: &'static str| {
+            TemplateGit::commit_and_push(&url, "acme/button", "Publish button", |template_path| {
+                fs::create_dir_all(template_path)
+                    .and_then(|_| fs::write(template_path.join("__var__.ts"), content))
+                    .map_err(|error| error.to_string())
+            })
+            .unwrap()
+        };
+
+        let commit = publish("button").unwrap();
+        let bare = Repository::open_bare(path.join("bare.git")).unwrap();
+        let head = bare.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id(), commit);
+        assert_eq!(head.parent_id(0).unwrap(), second);
+        assert_eq!(head.message(), Some("Publish button"));
+        assert!(publish("button").is_none());
+
+        let destination = path.join("published");
+        TemplateGit::clone(&url, None, &destination).unwrap();
+        let content = fs::read_to_string(destination.join(".gen/acme/button/__var__.ts")).unwrap();
+        assert_eq!(content, "button");
+
+        TemplateGit::commit_and_push(&url, "acme/button", "Unpublish button", |template_path| {
+            fs::remove_dir_all(template_path).map_err(|error| error.to_string())
+        })
+        .unwrap();
+        let head = bare.head().unwrap().peel_to_commit().unwrap();
+        assert!(head
+            .tree()
+            .unwrap()
+            .get_path(Path::new(".gen/acme"))
+            .is_err());
+        assert!(head
+            .tree()
+            .unwrap()
+            .get_path(Path::new(".gen/component"))
+            .is_ok());
+
+        // empty repository gets its first commit
+        let empty_path = path.join("empty.git");
+        Repository::init_bare(&empty_path).unwrap();
+        let commit = TemplateGit::commit_and_push(
+            empty_path.to_str().unwrap(),
+            "button",
+            "Publish button",
+            |template_path| {
+                fs::create_dir_all(template_path)
+                    .and_then(|_| fs::write(template_path.join("_.json"), "{}"))
+                    .map_err(|error| error.to_string())
+            },
+        )
+        .unwrap()
+        .unwrap();
+        let empty = Repository::open_bare(&empty_path).unwrap();
+        assert_eq!(empty.head().unwrap().peel_to_commit().unwrap().id(), commit);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
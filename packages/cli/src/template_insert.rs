@@ -0,0 +1,367 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// files where `#` starts a comment, other files can start with `#` markdown headings etc.
+const HASH_COMMENT_EXTENSIONS: [&str; 13] = [
+    "py", "sh", "bash", "zsh", "fish", "rb", "pl", "r", "yaml", "yml", "toml", "ini", "env",
+];
+const HASH_COMMENT_FILES: [&str; 5] = [
+    "Dockerfile",
+    "Makefile",
+    ".gitignore",
+    ".dockerignore",
+    ".env",
+];
+
+/**
+ * Where rendered content is inserted into existing file, set per file in template `_.json`:
+ * `"files": { "mod.rs": { "insert": { "after": "^pub mod " } } }`.
+ * Files without it are appended to the end, as before.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateInsert {
+    /// end of the file
+    #[default]
+    Append,
+    /// start of the file
+    Prepend,
+    /// start of the file, after leading comments, shebang, inner attributes & directives
+    Top,
+    /// after the last line matching the regex
+    After(String),
+    /// before the first line matching the regex
+    Before(String),
+    /// at the end of region between `gen:begin <name>` and `gen:end <name>` lines
+    Marker(String),
+}
+
+impl TemplateInsert {
+    /**
+     * Fragment ends with single new line and uses same line endings as existing file,
+     * so fragments join cleanly wherever they are inserted.
+     */
+    pub fn fragment(content: &str, existing: &str) -> String {
+        let content = content.replace("\r\n", "\n");
+        let content = content.trim_end_matches('\n');
+        if content.is_empty() {
+            return String::new();
+        }
+        let content = format!("{}\n", content);
+        if existing.contains("\r\n") {
+            return content.replace('\n', "\r\n");
+        }
+        content
+    }
+
+    /**
+     * Existing content with inserted fragment, missing anchor is an error.
+     */
+    pub fn insert(&self, path: &Path, existing: &str, fragment: &str) -> Result<String, String> {
+        let index = match self {
+            TemplateInsert::Append => existing.len(),
+            TemplateInsert::Prepend => 0,
+            TemplateInsert::Top => TemplateInsert::top_index(path, existing),
+            TemplateInsert::After(pattern) => {
+                let regex = TemplateInsert::regex(pattern)?;
+                TemplateInsert::lines(existing)
+                    .filter(|(_, line)| regex.is_match(line.trim_end_matches(['\r', '\n'])))
+                    .last()
+                    .map(|(start, line)| start + line.len())
+                    .ok_or_else(|| format!("No line matches '{}'", pattern))?
+            }
+            TemplateInsert::Before(pattern) => {
+                let regex = TemplateInsert::regex(pattern)?;
+                TemplateInsert::lines(existing)
+                    .find(|(_, line)| regex.is_match(line.trim_end_matches(['\r', '\n'])))
+                    .map(|(start, _)| start)
+                    .ok_or_else(|| format!("No line matches '{}'", pattern))?
+            }
+            TemplateInsert::Marker(name) => TemplateInsert::marker_index(existing, name)?,
+        };
+        Ok(TemplateInsert::insert_at(existing, index, fragment))
+    }
+
+    /**
+     * Existing content with fragment at index, which has to be start of a line or end of content.
+     */
+    pub fn insert_at(existing: &str, index: usize, fragment: &str) -> String {
+        let mut content = existing[..index].to_string();
+        // last line of the file does not have to end with new line
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push_str(if existing.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            });
+        }
+        content.push_str(fragment);
+        content.push_str(&existing[index..]);
+        content
+    }
+
+    fn regex(pattern: &str) -> Result<Regex, String> {
+        Regex::new(pattern).map_err(|error| format!("Invalid regex '{}': {}", pattern, error))
+    }
+
+    /**
+     * Lines with their start index, line includes its new line.
+     */
+    pub fn lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+        content.split_inclusive('\n').scan(0, |start, line| {
+            let line_start = *start;
+            *start += line.len();
+            Some((line_start, line))
+        })
+    }
+
+    /**
+     * End of leading comments, shebang, rust inner attributes and `"use ..."` directives.
+     * `#` comments are recognized only in files where `#` starts a comment, see `is_hash_comment`.
+     */
+    pub fn top_index(path: &Path, content: &str) -> usize {
+        let is_hash_comment = TemplateInsert::is_hash_comment(path);
+        let mut is_block_comment = false;
+        let mut index = 0;
+        for (start, line) in TemplateInsert::lines(content) {
+            let trimmed_line = line.trim();
+            let is_header = if is_block_comment || trimmed_line.starts_with("/*") {
+                is_block_comment = !trimmed_line.ends_with("*/");
+                true
+            } else {
+                trimmed_line.is_empty()
+                    || trimmed_line.starts_with("//")
+                    || (start == 0 && trimmed_line.starts_with("#!"))
+                    || trimmed_line.starts_with("#![")
+                    || (is_hash_comment && trimmed_line.starts_with('#'))
+                    || trimmed_line.starts_with("\"use ")
+                    || trimmed_line.starts_with("'use ")
+            };
+            if !is_header {
+                break;
+            }
+            index = start + line.len();
+        }
+        index
+    }
+
+    fn is_hash_comment(path: &Path) -> bool {
+        let file_name = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        HASH_COMMENT_FILES.contains(&file_name) || HASH_COMMENT_EXTENSIONS.contains(&extension)
+    }
+
+    fn marker_index(content: &str, name: &str) -> Result<usize, String> {
+        let begin = format!("gen:begin {}", name);
+        let end = format!("gen:end {}", name);
+        let is_marker = |line: &str, marker: &str| {
+            line.split_whitespace()
+                .collect::<Vec<_>>()
+                .windows(2)
+                .any(|words| format!("{} {}", words[0], words[1]) == marker)
+        };
+        let (begin_index, _) = TemplateInsert::lines(content)
+            .find(|(_, line)| is_marker(line, &begin))
+            .ok_or_else(|| format!("Marker '{}' not found", begin))?;
+        TemplateInsert::lines(&content[begin_index..])
+            .find(|(_, line)| is_marker(line, &end))
+            .map(|(start, _)| begin_index + start)
+            .ok_or_else(|| format!("Marker '{}' not found", end))
+    }
+}
+
+/**
+ * How appended / inserted content already present in the file is detected, set per file
+ * in template `_.json` next to `insert`, e.g. `{ "dedupe": "lines" }`.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateDedupe {
+    /// skip when all fragment lines are already in the file, one after another
+    #[default]
+    Fragment,
+    /// insert only lines which are not in the file yet
+    Lines,
+    /// always insert
+    Off,
+}
+
+impl TemplateDedupe {
+    /**
+     * Part of the fragment which is not in existing content yet, None when there is nothing
+     * to insert. Lines are compared without line endings & trailing whitespace.
+     */
+    pub fn dedupe(&self, existing: &str, fragment: &str) -> Option<String> {
+        let existing_lines = existing.lines().map(str::trim_end).collect::<Vec<_>>();
+        let fragment_lines = fragment.lines().map(str::trim_end).collect::<Vec<_>>();
+        let has_content = |lines: &[&str]| lines.iter().any(|line| !line.is_empty());
+        if !has_content(&fragment_lines) {
+            return Some(fragment.to_string());
+        }
+        match self {
+            TemplateDedupe::Off => Some(fragment.to_string()),
+            TemplateDedupe::Fragment => {
+                let is_present = existing_lines
+                    .windows(fragment_lines.len())
+                    .any(|lines| lines == fragment_lines.as_slice());
+                (!is_present).then(|| fragment.to_string())
+            }
+            TemplateDedupe::Lines => {
+                let lines = fragment
+                    .split_inclusive('\n')
+                    .filter(|line| {
+                        let line = line.trim_end();
+                        line.is_empty() || !existing_lines.contains(&line)
+                    })
+                    .collect::<Vec<_>>();
+                let trimmed_lines = lines.iter().map(|line| line.trim_end()).collect::<Vec<_>>();
+                has_content(&trimmed_lines).then(|| lines.concat())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplateDedupe, TemplateInsert};
+    use std::path::Path;
+
+    fn insert(insert: TemplateInsert, existing: &str, content: &str) -> Result<String, String> {
+        insert.insert(
+            Path::new("mod.rs"),
+            existing,
+            &TemplateInsert::fragment(content, existing),
+        )
+    }
+
+    #[test]
+    fn should_skip_only_known_headers_at_top() {
+        let top = |path: &str, content: &str| TemplateInsert::top_index(Path::new(path), content);
+        let script = "/**\n * Docs\n */\n'use strict';\nconst a = 1;\n";
+        assert_eq!(top("index.js", script), script.find("const").unwrap());
+        let python = "#!/usr/bin/env python\n# comment\nimport os\n";
+        assert_eq!(top("main.py", python), python.find("import").unwrap());
+        let rust = "#![allow(dead_code)]\n#[derive(Debug)]\nstruct A;\n";
+        assert_eq!(top("lib.rs", rust), rust.find("#[derive").unwrap());
+
+        assert_eq!(top("README.md", "# Title\n\ntext\n"), 0);
+        assert_eq!(top("list.md", "* item\n* item\n"), 0);
+        assert_eq!(top("main.c", "#include <stdio.h>\n"), 0);
+    }
+
+    #[test]
+    fn should_insert_at_anchor() {
+        let existing = "//! modules\n\npub mod a;\npub mod c;\n\nfn main() {}";
+        assert_eq!(
+            insert(TemplateInsert::Top, existing, "pub mod b;").unwrap(),
+            "//! modules\n\npub mod b;\npub mod a;\npub mod c;\n\nfn main() {}"
+        );
+        assert_eq!(
+            insert(
+                TemplateInsert::After("^pub mod .*;$".to_string()),
+                existing,
+                "pub mod d;"
+            )
+            .unwrap(),
+            "//! modules\n\npub mod a;\npub mod c;\npub mod d;\n\nfn main() {}"
+        );
+        assert_eq!(
+            insert(
+                TemplateInsert::Before("^fn main".to_string()),
+                existing,
+                "fn b() {}\n"
+            )
+            .unwrap(),
+            "//! modules\n\npub mod a;\npub mod c;\n\nfn b() {}\nfn main() {}"
+        );
+        assert_eq!(
+            insert(
+                TemplateInsert::After("main".to_string()),
+                existing,
+                "// end"
+            )
+            .unwrap(),
+            format!("{}\n// end\n", existing)
+        );
+        assert_eq!(
+            insert(TemplateInsert::Prepend, "b\n", "a").unwrap(),
+            "a\nb\n"
+        );
+        assert_eq!(
+            insert(TemplateInsert::Append, "a", "b\n\n").unwrap(),
+            "a\nb\n"
+        );
+        assert_eq!(
+            insert(TemplateInsert::Append, "a\r\n", "b\nc").unwrap(),
+            "a\r\nb\r\nc\r\n"
+        );
+
+        let routes = "const routes = [\n  // gen:begin routes\n  home,\n  // gen:end routes\n];\n";
+        assert_eq!(
+            insert(
+                TemplateInsert::Marker("routes".to_string()),
+                routes,
+                "  about,"
+            )
+            .unwrap(),
+            "const routes = [\n  // gen:begin routes\n  home,\n  about,\n  // gen:end routes\n];\n"
+        );
+    }
+
+    #[test]
+    fn should_fail_on_missing_anchor() {
+        let result = insert(TemplateInsert::After("^export".to_string()), "a\n", "b");
+        assert_eq!(result.unwrap_err(), "No line matches '^export'");
+        let result = insert(TemplateInsert::Marker("routes".to_string()), "a\n", "b");
+        assert_eq!(result.unwrap_err(), "Marker 'gen:begin routes' not found");
+        let result = insert(
+            TemplateInsert::Marker("routes".to_string()),
+            "// gen:begin routes\n",
+            "b",
+        );
+        assert_eq!(result.unwrap_err(), "Marker 'gen:end routes' not found");
+        assert!(insert(TemplateInsert::Before("(".to_string()), "a\n", "b").is_err());
+    }
+
+    #[test]
+    fn should_skip_present_fragments() {
+        let existing = "export * from './button';\r\nexport * from './input';\r\n";
+        let fragment = "export * from './button';\n";
+        assert_eq!(TemplateDedupe::Fragment.dedupe(existing, fragment), None);
+        assert_eq!(
+            TemplateDedupe::Off.dedupe(existing, fragment).as_deref(),
+            Some(fragment)
+        );
+        // prefix of existing line is not a duplicate
+        let fragment = "export * from './b';\n";
+        assert_eq!(
+            TemplateDedupe::Fragment
+                .dedupe(existing, fragment)
+                .as_deref(),
+            Some(fragment)
+        );
+
+        let fragment = "export * from './input';\nexport * from './select';\n";
+        assert_eq!(
+            TemplateDedupe::Fragment
+                .dedupe(existing, fragment)
+                .as_deref(),
+            Some(fragment)
+        );
+        assert_eq!(
+            TemplateDedupe::Lines.dedupe(existing, fragment).as_deref(),
+            Some("export * from './select';\n")
+        );
+        assert_eq!(
+            TemplateDedupe::Lines.dedupe(existing, "export * from './input';\n\n"),
+            None
+        );
+    }
+}
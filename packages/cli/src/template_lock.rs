@@ -0,0 +1,163 @@
+use crate::constants::TEMPLATE_LOCK_FILE;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/**
+ * Where fetched template came from, used by `gen update` to fetch it again.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TemplateLockEntry {
+    /// source as given to `gen fetch`
+    pub source: String,
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    /// resolved commit sha, only for git sources
+    pub commit: Option<String>,
+    /// template folder path inside of the source
+    pub subdir: String,
+    /// hash of template files when it was fetched, see `TemplateLock::hash_folder`
+    pub hash: String,
+}
+
+/**
+ * Fetched templates, stored in `.gen/gen.lock`.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TemplateLock {
+    pub templates: BTreeMap<String, TemplateLockEntry>,
+}
+
+impl TemplateLock {
+    pub fn lock_path(directory: &Path) -> PathBuf {
+        directory.join(TEMPLATE_LOCK_FILE)
+    }
+
+    pub fn load(directory: &Path) -> Result<TemplateLock, String> {
+        let lock_path = TemplateLock::lock_path(directory);
+        if !lock_path.exists() {
+            return Ok(TemplateLock::default());
+        }
+        let content = fs::read_to_string(&lock_path)
+            .map_err(|error| format!("Cannot read {}: {}", lock_path.display(), error))?;
+        serde_json::from_str(&content)
+            .map_err(|error| format!("Invalid {}: {}", lock_path.display(), error))
+    }
+
+    pub fn save(&self, directory: &Path) -> Result<(), String> {
+        let lock_path = TemplateLock::lock_path(directory);
+        let content = serde_json::to_string_pretty(&self).unwrap();
+        fs::create_dir_all(directory)
+            .and_then(|_| fs::write(&lock_path, format!("{}\n", content)))
+            .map_err(|error| format!("Cannot save {}: {}", lock_path.display(), error))
+    }
+
+    /**
+     * Hash of all files in the folder, file paths are part of the hash so renames are detected.
+     */
+    pub fn hash_folder(path: &Path) -> Result<String, String> {
+        let files = TemplateLock::list_files(path)?;
+        let mut hasher = Sha256::new();
+        for file in files.iter() {
+            let content = fs::read(path.join(file))
+                .map_err(|error| format!("Cannot read {}: {}", file, error))?;
+            hasher.update(file.as_bytes());
+            hasher.update([0]);
+            hasher.update(content.len().to_le_bytes());
+            hasher.update(&content);
+        }
+        let hash = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        Ok(format!("sha256:{}", hash))
+    }
+
+    /**
+     * Sorted relative paths of all files in the folder, with `/` separator.
+     */
+    pub fn list_files(path: &Path) -> Result<Vec<String>, String> {
+        let mut files = Vec::new();
+        TemplateLock::collect_files(path, path, &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    fn collect_files(root: &Path, path: &Path, files: &mut Vec<String>) -> Result<(), String> {
+        let entries = fs::read_dir(path)
+            .map_err(|error| format!("Cannot read {}: {}", path.display(), error))?;
+        for entry in entries {
+            let entry = entry.map_err(|error| error.to_string())?;
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                TemplateLock::collect_files(root, &entry_path, files)?;
+                continue;
+            }
+            files.push(TemplateLock::relative_path(root, &entry_path));
+        }
+        Ok(())
+    }
+
+    /**
+     * Path relative to root with `/` separator, so lock file is same on every platform.
+     */
+    pub fn relative_path(root: &Path, path: &Path) -> String {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplateLock, TemplateLockEntry};
+    use crate::template_git::TemplateGit;
+    use std::fs;
+
+    #[test]
+    fn should_hash_folder_and_save_lock() {
+        let path = TemplateGit::temp_directory("test-lock").unwrap();
+        let template_path = path.join("component");
+        fs::create_dir_all(template_path.join("src")).unwrap();
+        fs::write(template_path.join("src/__var__.ts"), "content").unwrap();
+        fs::write(template_path.join("_.json"), "{}").unwrap();
+
+        let hash = TemplateLock::hash_folder(&template_path).unwrap();
+        assert!(hash.starts_with("sha256:"));
+        assert_eq!(hash, TemplateLock::hash_folder(&template_path).unwrap());
+
+        fs::rename(
+            template_path.join("src/__var__.ts"),
+            template_path.join("src/__var__.tsx"),
+        )
+        .unwrap();
+        assert_ne!(hash, TemplateLock::hash_folder(&template_path).unwrap());
+
+        let mut lock = TemplateLock::load(&path).unwrap();
+        assert!(lock.templates.is_empty());
+        let entry = TemplateLockEntry {
+            source: "https://github.com/samuelgja/gen".to_string(),
+            git_ref: Some("main".to_string()),
+            commit: Some("abc".to_string()),
+            subdir: ".gen/component".to_string(),
+            hash,
+        };
+        lock.templates
+            .insert("component".to_string(), entry.to_owned());
+        lock.save(&path).unwrap();
+
+        let content = fs::read_to_string(TemplateLock::lock_path(&path)).unwrap();
+        assert!(content.contains("\"ref\": \"main\""));
+        let lock = TemplateLock::load(&path).unwrap();
+        assert_eq!(lock.templates.get("component").unwrap(), &entry);
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    config::Config,
+    constants::{TEMPLATE_HISTORY_FOLDER, TEMPLATE_ROOT_FOLDER},
+    template_plan::{TemplatePlan, TemplatePlanAction},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateManifestFile {
+    /// path relative to project root
+    pub path: String,
+    pub action: TemplatePlanAction,
+    /// exact content appended to the file in append mode
+    pub appended: Option<String>,
+    /// backup of overwritten file, relative to manifest backup folder
+    pub backup: Option<String>,
+}
+
+/**
+ * Record of one `gen use` run, stored in `.gen/.history/<id>.json`.
+ * It's used by `gen undo` and `gen destroy` to reverse the generation.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateManifest {
+    pub id: String,
+    pub template: String,
+    pub values: BTreeMap<String, String>,
+    pub files: Vec<TemplateManifestFile>,
+    /// folders created by the generation, relative to project root
+    pub directories: Vec<String>,
+    /// folder the manifest is stored in
+    #[serde(skip)]
+    pub history_path: PathBuf,
+}
+
+impl TemplateManifest {
+    /**
+     * History is kept in project `.gen`, projects without it (e.g. using only remote
+     * or global templates) keep it in `~/.gen/.history/<project>`, so `.gen` is not created.
+     */
+    pub fn history_path(cwd: &Path) -> PathBuf {
+        TemplateManifest::history_path_in(cwd, &Config::global_path())
+    }
+
+    fn history_path_in(cwd: &Path, global_path: &Path) -> PathBuf {
+        let root_path = cwd.join(TEMPLATE_ROOT_FOLDER);
+        if root_path.is_dir() {
+            return root_path.join(TEMPLATE_HISTORY_FOLDER);
+        }
+        let hash = Sha256::digest(cwd.to_string_lossy().as_bytes())
+            .iter()
+            .take(6)
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let name = cwd
+            .file_name()
+            .map(|name| name.to_string_lossy().replace(char::is_whitespace, "-"))
+            .unwrap_or_default();
+        global_path
+            .join(TEMPLATE_HISTORY_FOLDER)
+            .join(format!("{}-{}", name, hash))
+    }
+
+    pub fn backup_path(&self) -> PathBuf {
+        self.history_path.join(&self.id)
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.history_path.join(format!("{}.json", self.id))
+    }
+
+    /**
+     * Create manifest from the plan before it's applied, so overwritten files can be backed up.
+     */
+    pub fn from_plan(
+        cwd: &Path,
+        template: &str,
+        values: &HashMap<String, String>,
+        plan: &TemplatePlan,
+    ) -> TemplateManifest {
+        let history_path = TemplateManifest::history_path(cwd);
+        let mut id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        while history_path.join(format!("{}.json", id)).exists() {
+            id += 1;
+        }
+
+        let files = plan
+            .files
+            .iter()
+            .map(|file| {
+                let path = TemplatePlan::display_path(&file.path, cwd);
+                TemplateManifestFile {
+                    action: file.action,
+                    appended: match file.action {
+                        TemplatePlanAction::Append => Some(file.content.to_owned()),
+                        _ => None,
+                    },
+                    backup: match file.action {
+                        TemplatePlanAction::Overwrite
+                        | TemplatePlanAction::Merge
+                        | TemplatePlanAction::Register => Some(path.to_owned()),
+                        _ => None,
+                    },
+                    path,
+                }
+            })
+            .collect();
+
+        TemplateManifest {
+            id: id.to_string(),
+            template: template.to_string(),
+            values: values
+                .iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+            files,
+            directories: Vec::new(),
+            history_path,
+        }
+    }
+
+    /**
+     * Copy files which are going to be overwritten to the backup folder.
+     */
+    pub fn save_backups(&self, cwd: &Path) -> Result<(), String> {
+        let backup_path = self.backup_path();
+        for file in self.files.iter() {
+            if let Some(backup) = &file.backup {
+                let destination = backup_path.join(backup);
+                fs::create_dir_all(destination.parent().unwrap())
+                    .and_then(|_| fs::copy(cwd.join(&file.path), &destination))
+                    .map_err(|error| format!("Cannot backup {}: {}", file.path, error))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let manifest_path = self.manifest_path();
+        let content = serde_json::to_string_pretty(&self).unwrap();
+        fs::create_dir_all(manifest_path.parent().unwrap())
+            .and_then(|_| fs::write(&manifest_path, content))
+            .map_err(|error| format!("Cannot save generation manifest: {}", error))
+    }
+
+    pub fn remove(&self) {
+        let _ = fs::remove_file(self.manifest_path());
+        let _ = fs::remove_dir_all(self.backup_path());
+    }
+
+    /**
+     * All manifests, sorted from the oldest to the newest.
+     */
+    pub fn load_all(cwd: &Path) -> Vec<TemplateManifest> {
+        let history_path = TemplateManifest::history_path(cwd);
+        let entries = fs::read_dir(&history_path);
+        if entries.is_err() {
+            return Vec::new();
+        }
+        let mut manifests = entries
+            .unwrap()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                let content = fs::read_to_string(path).ok()?;
+                let mut manifest = serde_json::from_str::<TemplateManifest>(&content).ok()?;
+                manifest.history_path = history_path.to_owned();
+                Some(manifest)
+            })
+            .collect::<Vec<_>>();
+        manifests.sort_by_key(|manifest| manifest.id.parse::<u128>().unwrap_or_default());
+        manifests
+    }
+
+    pub fn load_last(cwd: &Path) -> Option<TemplateManifest> {
+        TemplateManifest::load_all(cwd).pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateManifest;
+    use crate::{
+        template_git::TemplateGit,
+        template_plan::{TemplatePlan, TemplatePlanFile},
+    };
+    use std::{collections::HashMap, fs};
+
+    #[test]
+    fn should_keep_history_outside_of_project_without_gen() {
+        let path = TemplateGit::temp_directory("test-manifest").unwrap();
+        let cwd = path.join("project");
+        let global_path = path.join("home/.gen");
+        fs::create_dir_all(&cwd).unwrap();
+
+        let history_path = TemplateManifest::history_path_in(&cwd, &global_path);
+        assert!(history_path.starts_with(global_path.join(".history")));
+        let mut plan = TemplatePlan::new();
+        plan.files.push(TemplatePlanFile::new(
+            cwd.join("button.ts"),
+            "button".to_string(),
+            false,
+        ));
+        let mut manifest = TemplateManifest::from_plan(&cwd, "remote", &HashMap::new(), &plan);
+        manifest.history_path = history_path.to_owned();
+        manifest.save().unwrap();
+        assert!(!cwd.join(".gen").exists());
+        assert!(history_path.join(format!("{}.json", manifest.id)).is_file());
+
+        fs::create_dir_all(cwd.join(".gen")).unwrap();
+        assert_eq!(
+            TemplateManifest::history_path_in(&cwd, &global_path),
+            cwd.join(".gen/.history")
+        );
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
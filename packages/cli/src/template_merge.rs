@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use toml_edit::{DocumentMut, Item};
+
+/**
+ * Structured merge of rendered template into existing JSON, YAML or TOML file, set per file
+ * in template `_.json`: `"files": { "package.json": { "merge": "auto" } }`.
+ * Objects are merged deeply, missing array items are appended, and a scalar which would be
+ * overwritten with another value is a conflict.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateMerge {
+    /// format by file extension
+    Auto,
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl TemplateMerge {
+    fn format(&self, path: &Path) -> Result<TemplateMerge, String> {
+        if *self != TemplateMerge::Auto {
+            return Ok(*self);
+        }
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "json" => Ok(TemplateMerge::Json),
+            "yaml" | "yml" => Ok(TemplateMerge::Yaml),
+            "toml" => Ok(TemplateMerge::Toml),
+            _ => Err(format!(
+                "Cannot detect merge format of {}, use json, yaml or toml",
+                path.to_str().unwrap()
+            )),
+        }
+    }
+
+    /**
+     * Existing content with merged fragment, conflicts are returned as error.
+     */
+    pub fn merge(&self, path: &Path, existing: &str, fragment: &str) -> Result<String, String> {
+        let mut conflicts = Vec::new();
+        let content = match self.format(path)? {
+            TemplateMerge::Json => TemplateMerge::merge_json(existing, fragment, &mut conflicts),
+            TemplateMerge::Yaml => TemplateMerge::merge_yaml(existing, fragment, &mut conflicts),
+            _ => TemplateMerge::merge_toml(existing, fragment, &mut conflicts),
+        }
+        .map_err(|error| format!("Cannot merge {}: {}", path.to_str().unwrap(), error))?;
+        if !conflicts.is_empty() {
+            return Err(format!(
+                "Merge conflicts in {}:\n{}",
+                path.to_str().unwrap(),
+                conflicts.join("\n")
+            ));
+        }
+        Ok(content)
+    }
+
+    fn key_path(parent: &str, key: &str) -> String {
+        if parent.is_empty() {
+            return key.to_string();
+        }
+        format!("{}.{}", parent, key)
+    }
+
+    fn conflict(key_path: &str, existing: &str, value: &str) -> String {
+        format!("  {}: existing {}, template {}", key_path, existing, value)
+    }
+
+    /**
+     * Output keeps key order and indentation of existing file.
+     */
+    fn merge_json(
+        existing: &str,
+        fragment: &str,
+        conflicts: &mut Vec<String>,
+    ) -> Result<String, String> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(existing).map_err(|error| error.to_string())?;
+        let fragment: serde_json::Value = serde_json::from_str(fragment)
+            .map_err(|error| format!("template is not valid json: {}", error))?;
+        TemplateMerge::merge_json_value(&mut value, fragment, "", conflicts);
+
+        let indent = existing
+            .lines()
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+        let mut content = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut content, formatter);
+        value
+            .serialize(&mut serializer)
+            .map_err(|error| error.to_string())?;
+        let mut content = String::from_utf8(content).unwrap();
+        if existing.ends_with('\n') {
+            content.push('\n');
+        }
+        Ok(content)
+    }
+
+    fn merge_json_value(
+        value: &mut serde_json::Value,
+        fragment: serde_json::Value,
+        key_path: &str,
+        conflicts: &mut Vec<String>,
+    ) {
+        match (value, fragment) {
+            (serde_json::Value::Object(object), serde_json::Value::Object(fragment)) => {
+                for (key, fragment_value) in fragment {
+                    let child_path = TemplateMerge::key_path(key_path, &key);
+                    match object.get_mut(&key) {
+                        Some(value) => TemplateMerge::merge_json_value(
+                            value,
+                            fragment_value,
+                            &child_path,
+                            conflicts,
+                        ),
+                        None => {
+                            object.insert(key, fragment_value);
+                        }
+                    }
+                }
+            }
+            (serde_json::Value::Array(items), serde_json::Value::Array(fragment)) => {
+                for item in fragment {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            (value, fragment) if *value != fragment => {
+                conflicts.push(TemplateMerge::conflict(
+                    key_path,
+                    &value.to_string(),
+                    &fragment.to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * YAML is written again by serde_yaml, key order is kept but comments are lost.
+     */
+    fn merge_yaml(
+        existing: &str,
+        fragment: &str,
+        conflicts: &mut Vec<String>,
+    ) -> Result<String, String> {
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str(existing).map_err(|error| error.to_string())?;
+        let fragment: serde_yaml::Value = serde_yaml::from_str(fragment)
+            .map_err(|error| format!("template is not valid yaml: {}", error))?;
+        TemplateMerge::merge_yaml_value(&mut value, fragment, "", conflicts);
+        serde_yaml::to_string(&value).map_err(|error| error.to_string())
+    }
+
+    fn merge_yaml_value(
+        value: &mut serde_yaml::Value,
+        fragment: serde_yaml::Value,
+        key_path: &str,
+        conflicts: &mut Vec<String>,
+    ) {
+        let to_string = |value: &serde_yaml::Value| {
+            serde_yaml::to_string(value)
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        match (value, fragment) {
+            (serde_yaml::Value::Mapping(mapping), serde_yaml::Value::Mapping(fragment)) => {
+                for (key, fragment_value) in fragment {
+                    let child_path = TemplateMerge::key_path(key_path, &to_string(&key));
+                    match mapping.get_mut(&key) {
+                        Some(value) => TemplateMerge::merge_yaml_value(
+                            value,
+                            fragment_value,
+                            &child_path,
+                            conflicts,
+                        ),
+                        None => {
+                            mapping.insert(key, fragment_value);
+                        }
+                    }
+                }
+            }
+            (serde_yaml::Value::Sequence(items), serde_yaml::Value::Sequence(fragment)) => {
+                for item in fragment {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            (value, fragment) if *value != fragment => {
+                conflicts.push(TemplateMerge::conflict(
+                    key_path,
+                    &to_string(value),
+                    &to_string(&fragment),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * TOML is edited in place, formatting & comments of existing file are kept.
+     */
+    fn merge_toml(
+        existing: &str,
+        fragment: &str,
+        conflicts: &mut Vec<String>,
+    ) -> Result<String, String> {
+        let mut document = existing
+            .parse::<DocumentMut>()
+            .map_err(|error| error.to_string())?;
+        let fragment = fragment
+            .parse::<DocumentMut>()
+            .map_err(|error| format!("template is not valid toml: {}", error))?;
+        TemplateMerge::merge_toml_item(
+            document.as_item_mut(),
+            fragment.as_item().to_owned(),
+            "",
+            conflicts,
+        );
+        Ok(document.to_string())
+    }
+
+    fn merge_toml_item(
+        item: &mut Item,
+        fragment: Item,
+        key_path: &str,
+        conflicts: &mut Vec<String>,
+    ) {
+        if let (Some(table), Some(fragment_table)) =
+            (item.as_table_like_mut(), fragment.as_table_like())
+        {
+            for (key, fragment_item) in fragment_table.iter() {
+                let child_path = TemplateMerge::key_path(key_path, key);
+                match table.get_mut(key) {
+                    Some(item) => TemplateMerge::merge_toml_item(
+                        item,
+                        fragment_item.to_owned(),
+                        &child_path,
+                        conflicts,
+                    ),
+                    None => {
+                        table.insert(key, fragment_item.to_owned());
+                    }
+                }
+            }
+            return;
+        }
+
+        // values are compared without comments & whitespace around them
+        let display = |item: &Item| match item.as_value() {
+            Some(value) => value.to_owned().decorated("", "").to_string(),
+            None => item.to_string().trim().to_string(),
+        };
+        if let (Some(array), Some(fragment_array)) = (item.as_array_mut(), fragment.as_array()) {
+            for value in fragment_array.iter() {
+                let is_present = array.iter().any(|item| {
+                    item.to_owned().decorated("", "").to_string()
+                        == value.to_owned().decorated("", "").to_string()
+                });
+                if !is_present {
+                    array.push_formatted(value.to_owned().decorated(" ", ""));
+                }
+            }
+            return;
+        }
+        if display(item) != display(&fragment) {
+            conflicts.push(TemplateMerge::conflict(
+                key_path,
+                &display(item),
+                &display(&fragment),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateMerge;
+    use std::path::Path;
+
+    #[test]
+    fn should_merge_json() {
+        let existing = "{\n    \"name\": \"app\",\n    \"scripts\": {\n        \"build\": \"tsc\"\n    },\n    \"files\": [\"dist\"]\n}\n";
+        let fragment =
+            r#"{ "scripts": { "test": "vitest" }, "files": ["dist", "src"], "name": "app" }"#;
+        let content = TemplateMerge::Auto
+            .merge(Path::new("package.json"), existing, fragment)
+            .unwrap();
+        assert_eq!(
+            content,
+            "{\n    \"name\": \"app\",\n    \"scripts\": {\n        \"build\": \"tsc\",\n        \"test\": \"vitest\"\n    },\n    \"files\": [\n        \"dist\",\n        \"src\"\n    ]\n}\n"
+        );
+
+        let fragment = r#"{ "scripts": { "build": "vite build" } }"#;
+        let error = TemplateMerge::Json
+            .merge(Path::new("package.json"), existing, fragment)
+            .unwrap_err();
+        assert!(error.contains("scripts.build: existing \"tsc\", template \"vite build\""));
+    }
+
+    #[test]
+    fn should_merge_toml_and_yaml() {
+        let existing = "[package]\nname = \"app\" # comment\n\n[dependencies]\nserde = \"1\"\n";
+        let fragment = "[dependencies]\nregex = \"1.10\"\n";
+        let content = TemplateMerge::Auto
+            .merge(Path::new("Cargo.toml"), existing, fragment)
+            .unwrap();
+        assert_eq!(
+            content,
+            "[package]\nname = \"app\" # comment\n\n[dependencies]\nserde = \"1\"\nregex = \"1.10\"\n"
+        );
+        let error = TemplateMerge::Toml
+            .merge(
+                Path::new("Cargo.toml"),
+                existing,
+                "package = { name = \"x\" }",
+            )
+            .unwrap_err();
+        assert!(error.contains("package.name: existing \"app\", template \"x\""));
+
+        let existing = "services:\n  web:\n    image: app\n";
+        let fragment = "services:\n  db:\n    image: postgres\n";
+        let content = TemplateMerge::Auto
+            .merge(Path::new("compose.yml"), existing, fragment)
+            .unwrap();
+        assert_eq!(
+            content,
+            "services:\n  web:\n    image: app\n  db:\n    image: postgres\n"
+        );
+        assert!(TemplateMerge::Auto
+            .merge(Path::new("config.ini"), existing, fragment)
+            .is_err());
+    }
+}
@@ -0,0 +1,613 @@
+use crate::{
+    template_insert::{TemplateDedupe, TemplateInsert},
+    template_merge::TemplateMerge,
+    template_register::TemplateRegister,
+};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplatePlanAction {
+    Create,
+    Overwrite,
+    Append,
+    Merge,
+    Register,
+}
+
+impl Display for TemplatePlanAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplatePlanAction::Create => write!(f, "create"),
+            TemplatePlanAction::Overwrite => write!(f, "overwrite"),
+            TemplatePlanAction::Append => write!(f, "append"),
+            TemplatePlanAction::Merge => write!(f, "merge"),
+            TemplatePlanAction::Register => write!(f, "register"),
+        }
+    }
+}
+
+/**
+ * One output file of template usage, with already rendered content.
+ */
+#[derive(Debug, Clone)]
+pub struct TemplatePlanFile {
+    pub path: PathBuf,
+    pub content: String,
+    pub action: TemplatePlanAction,
+    pub existing_content: Option<String>,
+    /// where content is inserted in append mode, raw bytes are appended without it
+    pub insert: Option<TemplateInsert>,
+    pub dedupe: TemplateDedupe,
+    /// declared in its parent module file by `TemplatePlan::register_files`
+    pub register: bool,
+}
+
+impl TemplatePlanFile {
+    pub fn new(path: PathBuf, content: String, is_append_mode: bool) -> TemplatePlanFile {
+        let existing_content = if path.is_file() {
+            // binary files cannot be diffed, so they are treated as empty
+            Some(fs::read_to_string(&path).unwrap_or_default())
+        } else {
+            None
+        };
+
+        let action = if existing_content.is_none() {
+            TemplatePlanAction::Create
+        } else if is_append_mode {
+            TemplatePlanAction::Append
+        } else {
+            TemplatePlanAction::Overwrite
+        };
+
+        TemplatePlanFile {
+            path,
+            content,
+            action,
+            existing_content,
+            insert: None,
+            dedupe: TemplateDedupe::Off,
+            register: false,
+        }
+    }
+
+    /**
+     * Insert content at anchor instead of end of existing file, file is created when it is missing.
+     * Content is normalized to the file line endings, missing anchor is reported before
+     * anything is written.
+     */
+    pub fn with_insert(
+        mut self,
+        insert: TemplateInsert,
+        dedupe: TemplateDedupe,
+    ) -> Result<TemplatePlanFile, String> {
+        if let Some(existing_content) = &self.existing_content {
+            let content = TemplateInsert::fragment(&self.content, existing_content);
+            insert
+                .insert(&self.path, existing_content, &content)
+                .map_err(|error| format!("{} in {}", error, self.path.to_str().unwrap()))?;
+            self.action = TemplatePlanAction::Append;
+            self.content = content;
+        }
+        self.insert = Some(insert);
+        self.dedupe = dedupe;
+        Ok(self)
+    }
+
+    /**
+     * Merge content into existing JSON, YAML or TOML file, file is created when it is missing.
+     * Content becomes whole merged file, conflicts are reported before anything is written.
+     */
+    pub fn with_merge(mut self, merge: TemplateMerge) -> Result<TemplatePlanFile, String> {
+        if let Some(existing_content) = &self.existing_content {
+            self.content = merge.merge(&self.path, existing_content, &self.content)?;
+            self.action = TemplatePlanAction::Merge;
+        }
+        Ok(self)
+    }
+
+    /**
+     * Content of the file after template is applied.
+     */
+    pub fn final_content(&self) -> String {
+        match self.action {
+            TemplatePlanAction::Append => {
+                let existing_content = self.existing_content.as_ref().unwrap();
+                match &self.insert {
+                    // anchor is checked when insert is set
+                    Some(insert) => insert
+                        .insert(&self.path, existing_content, &self.content)
+                        .unwrap_or_else(|_| existing_content.to_owned()),
+                    None => format!("{}{}", existing_content, self.content),
+                }
+            }
+            _ => self.content.to_owned(),
+        }
+    }
+
+    pub fn unified_diff(&self, cwd: &Path) -> String {
+        let display_path = TemplatePlan::display_path(&self.path, cwd);
+        let old_content = self.existing_content.to_owned().unwrap_or_default();
+        let new_content = self.final_content();
+        let old_header = if self.existing_content.is_some() {
+            format!("a/{}", display_path)
+        } else {
+            "/dev/null".to_string()
+        };
+
+        TextDiff::from_lines(&old_content, &new_content)
+            .unified_diff()
+            .header(&old_header, &format!("b/{}", display_path))
+            .to_string()
+    }
+}
+
+/**
+ * File written by plan apply, keeps original bytes so it can be restored on failure.
+ */
+struct TemplatePlanApplied {
+    path: PathBuf,
+    original: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TemplatePlan {
+    pub files: Vec<TemplatePlanFile>,
+}
+
+impl TemplatePlan {
+    pub fn new() -> TemplatePlan {
+        TemplatePlan { files: Vec::new() }
+    }
+
+    /**
+     * Appended / inserted / merged content already present in the file is not written again,
+     * returns paths of skipped files.
+     */
+    pub fn skip_present_content(&mut self) -> Vec<PathBuf> {
+        let mut skipped = Vec::new();
+        self.files.retain_mut(|file| {
+            let existing_content = file.existing_content.as_deref().unwrap_or_default();
+            let is_merge = matches!(
+                file.action,
+                TemplatePlanAction::Merge | TemplatePlanAction::Register
+            );
+            if is_merge && file.content == existing_content {
+                skipped.push(file.path.to_owned());
+                return false;
+            }
+            if file.action != TemplatePlanAction::Append {
+                return true;
+            }
+            match file.dedupe.dedupe(existing_content, &file.content) {
+                Some(content) => {
+                    file.content = content;
+                    true
+                }
+                None => {
+                    skipped.push(file.path.to_owned());
+                    false
+                }
+            }
+        });
+        skipped
+    }
+
+    /**
+     * Declare files marked with `register` in their module files. It is done once the plan is
+     * confirmed, so files declined for overwrite are not declared.
+     */
+    pub fn register_files(&mut self) -> Result<(), String> {
+        let paths = self
+            .files
+            .iter()
+            .filter(|file| file.register)
+            .map(|file| file.path.to_owned())
+            .collect::<Vec<_>>();
+        for path in paths {
+            self.register(&path)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Declare generated file in its parent module file, module file is created when it is missing.
+     * Module file already in the plan gets the declaration in its content.
+     */
+    pub fn register(&mut self, path: &Path) -> Result<(), String> {
+        let register = TemplateRegister::new(path)?;
+        let module_path = &register.module_path;
+        if let Some(file) = self.files.iter_mut().find(|file| &file.path == module_path) {
+            if file.action == TemplatePlanAction::Append {
+                return Err(format!(
+                    "Cannot register {} in appended file {}",
+                    path.to_str().unwrap(),
+                    module_path.to_str().unwrap()
+                ));
+            }
+            file.content = register.register(&file.content);
+            return Ok(());
+        }
+
+        let mut file = TemplatePlanFile::new(module_path.to_owned(), String::new(), false);
+        file.content = register.register(file.existing_content.as_deref().unwrap_or_default());
+        if file.existing_content.is_some() {
+            file.action = TemplatePlanAction::Register;
+        }
+        self.files.push(file);
+        Ok(())
+    }
+
+    pub fn display_path(path: &Path, cwd: &Path) -> String {
+        path.strip_prefix(cwd)
+            .unwrap_or(path)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /**
+     * Apply whole plan at once. All files are staged to temp files first and then renamed into place.
+     * If any step fails, already written files are restored to the original content,
+     * created files and folders are removed, so project is never left half generated.
+     * Returns list of created folders.
+     */
+    pub fn apply(&self) -> Result<Vec<PathBuf>, String> {
+        let mut created_directories = Vec::new();
+        let mut staged = Vec::new();
+        let mut applied = Vec::new();
+
+        let result = self
+            .stage(&mut created_directories, &mut staged)
+            .and_then(|_| TemplatePlan::commit(&staged, &mut applied));
+
+        if let Err(error) = result {
+            for (staged_path, _) in staged.iter() {
+                let _ = fs::remove_file(staged_path);
+            }
+            let rollback_errors = TemplatePlan::rollback(&applied, &created_directories);
+            if !rollback_errors.is_empty() {
+                return Err(format!(
+                    "{}. Rollback failed for: {}",
+                    error,
+                    rollback_errors.join(", ")
+                ));
+            }
+            return Err(error);
+        }
+        Ok(created_directories)
+    }
+
+    fn stage<'a>(
+        &'a self,
+        created_directories: &mut Vec<PathBuf>,
+        staged: &mut Vec<(PathBuf, &'a TemplatePlanFile)>,
+    ) -> Result<(), String> {
+        for file in self.files.iter() {
+            let parent = file.path.parent().unwrap();
+            TemplatePlan::create_directories(parent, created_directories)?;
+
+            let mut bytes = Vec::new();
+            if file.action == TemplatePlanAction::Append {
+                bytes = fs::read(&file.path).map_err(|error| {
+                    format!("Cannot read {}: {}", file.path.to_str().unwrap(), error)
+                })?;
+            }
+            match (&file.insert, file.action) {
+                (Some(insert), TemplatePlanAction::Append) => {
+                    let existing_content = String::from_utf8(bytes).map_err(|_| {
+                        format!(
+                            "Cannot insert into binary file {}",
+                            file.path.to_str().unwrap()
+                        )
+                    })?;
+                    let content = insert
+                        .insert(&file.path, &existing_content, &file.content)
+                        .map_err(|error| format!("{} in {}", error, file.path.to_str().unwrap()))?;
+                    bytes = content.into_bytes();
+                }
+                _ => bytes.extend_from_slice(file.content.as_bytes()),
+            }
+
+            let staged_path = TemplatePlan::staged_path(&file.path);
+            fs::write(&staged_path, bytes).map_err(|error| {
+                format!("Cannot write {}: {}", staged_path.to_str().unwrap(), error)
+            })?;
+            staged.push((staged_path.to_owned(), file));
+
+            if let Ok(metadata) = fs::metadata(&file.path) {
+                let _ = fs::set_permissions(&staged_path, metadata.permissions());
+            }
+        }
+        Ok(())
+    }
+
+    fn commit(
+        staged: &[(PathBuf, &TemplatePlanFile)],
+        applied: &mut Vec<TemplatePlanApplied>,
+    ) -> Result<(), String> {
+        for (staged_path, file) in staged.iter() {
+            let original = if file.path.exists() {
+                let original = fs::read(&file.path).map_err(|error| {
+                    format!("Cannot read {}: {}", file.path.to_str().unwrap(), error)
+                })?;
+                Some(original)
+            } else {
+                None
+            };
+
+            fs::rename(staged_path, &file.path).map_err(|error| {
+                format!("Cannot write {}: {}", file.path.to_str().unwrap(), error)
+            })?;
+            applied.push(TemplatePlanApplied {
+                path: file.path.to_owned(),
+                original,
+            });
+        }
+        Ok(())
+    }
+
+    fn rollback(applied: &[TemplatePlanApplied], created_directories: &[PathBuf]) -> Vec<String> {
+        let mut errors = Vec::new();
+        for item in applied.iter().rev() {
+            let result = match &item.original {
+                Some(original) => fs::write(&item.path, original),
+                None => fs::remove_file(&item.path),
+            };
+            if result.is_err() {
+                errors.push(item.path.to_str().unwrap().to_string());
+            }
+        }
+        for directory in created_directories.iter().rev() {
+            // only empty folders are removed
+            let _ = fs::remove_dir(directory);
+        }
+        errors
+    }
+
+    fn create_directories(
+        directory: &Path,
+        created_directories: &mut Vec<PathBuf>,
+    ) -> Result<(), String> {
+        if directory.is_dir() {
+            return Ok(());
+        }
+        if let Some(parent) = directory.parent() {
+            TemplatePlan::create_directories(parent, created_directories)?;
+        }
+        fs::create_dir(directory).map_err(|error| {
+            format!(
+                "Cannot create folder {}: {}",
+                directory.to_str().unwrap(),
+                error
+            )
+        })?;
+        created_directories.push(directory.to_path_buf());
+        Ok(())
+    }
+
+    fn staged_path(path: &Path) -> PathBuf {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        path.with_file_name(format!(".{}.gen-{}.tmp", file_name, std::process::id()))
+    }
+
+    pub fn print_dry_run(&self, cwd: &Path) {
+        println!(
+            "{}",
+            "🔎 Dry run, nothing will be written:".bold().magenta()
+        );
+        println!();
+        self.print_changes(cwd);
+    }
+
+    /**
+     * List of files with their action, followed by diff of each file.
+     */
+    pub fn print_changes(&self, cwd: &Path) {
+        let width = 10;
+        for file in self.files.iter() {
+            let action = file.action.to_string();
+            let action = match file.action {
+                TemplatePlanAction::Create => action.green(),
+                TemplatePlanAction::Overwrite => action.red(),
+                TemplatePlanAction::Append
+                | TemplatePlanAction::Merge
+                | TemplatePlanAction::Register => action.yellow(),
+            };
+            println!(
+                "{:width$} {}",
+                action.bold(),
+                TemplatePlan::display_path(&file.path, cwd)
+            );
+        }
+
+        for file in self.files.iter() {
+            println!();
+            for line in file.unified_diff(cwd).lines() {
+                let line = if line.starts_with("+++") || line.starts_with("---") {
+                    line.bold()
+                } else if line.starts_with('+') {
+                    line.green()
+                } else if line.starts_with('-') {
+                    line.red()
+                } else if line.starts_with("@@") {
+                    line.cyan()
+                } else {
+                    line.normal()
+                };
+                println!("{}", line);
+            }
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplatePlan, TemplatePlanAction, TemplatePlanFile};
+    use crate::{
+        template_git::TemplateGit,
+        template_insert::{TemplateDedupe, TemplateInsert},
+    };
+    use std::fs;
+
+    #[test]
+    fn should_detect_plan_actions() {
+        let directory = TemplateGit::temp_directory("test-plan").unwrap();
+        let existing_path = directory.join("index.ts");
+        fs::write(&existing_path, "export * from './a';\n").unwrap();
+
+        let file = TemplatePlanFile::new(
+            directory.join("new.ts"),
+            "export const b = 1;\n".to_string(),
+            false,
+        );
+        assert_eq!(file.action, TemplatePlanAction::Create);
+
+        let file = TemplatePlanFile::new(
+            existing_path.clone(),
+            "export * from './b';\n".to_string(),
+            true,
+        );
+        assert_eq!(file.action, TemplatePlanAction::Append);
+        assert_eq!(
+            file.final_content(),
+            "export * from './a';\nexport * from './b';\n"
+        );
+        let diff = file.unified_diff(&directory);
+        assert!(diff.contains("--- a/index.ts"));
+        assert!(diff.contains("+export * from './b';"));
+
+        let file = TemplatePlanFile::new(existing_path, "replaced\n".to_string(), false);
+        assert_eq!(file.action, TemplatePlanAction::Overwrite);
+        assert!(file
+            .unified_diff(&directory)
+            .contains("-export * from './a';"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn should_rollback_plan_on_failure() {
+        let directory = TemplateGit::temp_directory("test-plan-rollback").unwrap();
+        let existing_path = directory.join("index.ts");
+        fs::write(&existing_path, "export * from './a';\n").unwrap();
+        // file in place of folder, so the last file cannot be written
+        fs::write(directory.join("blocked"), "").unwrap();
+
+        let mut plan = TemplatePlan::new();
+        plan.files.push(TemplatePlanFile::new(
+            existing_path.clone(),
+            "export * from './b';\n".to_string(),
+            true,
+        ));
+        plan.files.push(TemplatePlanFile::new(
+            directory.join("new/button.ts"),
+            "button".to_string(),
+            false,
+        ));
+        plan.files.push(TemplatePlanFile::new(
+            directory.join("blocked/file.ts"),
+            "file".to_string(),
+            false,
+        ));
+
+        assert!(plan.apply().is_err());
+        assert_eq!(
+            fs::read_to_string(&existing_path).unwrap(),
+            "export * from './a';\n"
+        );
+        assert!(!directory.join("new").exists());
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 2);
+
+        plan.files.pop();
+        assert!(plan.apply().is_ok());
+        assert_eq!(
+            fs::read_to_string(&existing_path).unwrap(),
+            "export * from './a';\nexport * from './b';\n"
+        );
+        assert_eq!(
+            fs::read_to_string(directory.join("new/button.ts")).unwrap(),
+            "button"
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn should_insert_into_existing_file() {
+        let directory = TemplateGit::temp_directory("test-plan-insert").unwrap();
+        let existing_path = directory.join("mod.rs");
+        fs::write(&existing_path, "pub mod a;\n\npub fn run() {}\n").unwrap();
+
+        let file = TemplatePlanFile::new(existing_path.clone(), "pub mod b;".to_string(), false)
+            .with_insert(TemplateInsert::Top, TemplateDedupe::Fragment)
+            .unwrap();
+        assert_eq!(file.action, TemplatePlanAction::Append);
+        assert_eq!(file.content, "pub mod b;\n");
+
+        let missing = TemplatePlanFile::new(existing_path.clone(), "b".to_string(), true)
+            .with_insert(
+                TemplateInsert::Marker("mods".to_string()),
+                TemplateDedupe::Fragment,
+            );
+        assert!(missing.unwrap_err().contains("gen:begin mods"));
+
+        let created = TemplatePlanFile::new(directory.join("new.rs"), "b".to_string(), true)
+            .with_insert(
+                TemplateInsert::Marker("mods".to_string()),
+                TemplateDedupe::Fragment,
+            )
+            .unwrap();
+        assert_eq!(created.action, TemplatePlanAction::Create);
+
+        let mut plan = TemplatePlan::new();
+        plan.files.push(file);
+        assert!(plan.skip_present_content().is_empty());
+        plan.apply().unwrap();
+        assert_eq!(
+            fs::read_to_string(&existing_path).unwrap(),
+            "pub mod b;\npub mod a;\n\npub fn run() {}\n"
+        );
+
+        // second run finds the line already there
+        let mut plan = TemplatePlan::new();
+        plan.files.push(
+            TemplatePlanFile::new(existing_path.clone(), "pub mod b;".to_string(), true)
+                .with_insert(TemplateInsert::Append, TemplateDedupe::Fragment)
+                .unwrap(),
+        );
+        assert_eq!(plan.skip_present_content(), vec![existing_path.clone()]);
+        assert!(plan.files.is_empty());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn should_register_only_confirmed_files() {
+        let directory = TemplateGit::temp_directory("test-plan-register").unwrap();
+        fs::write(directory.join("b.rs"), "pub struct B;\n").unwrap();
+
+        let mut plan = TemplatePlan::new();
+        for name in ["a.rs", "b.rs", "mod.rs"] {
+            let mut file = TemplatePlanFile::new(directory.join(name), String::new(), false);
+            file.register = name != "mod.rs";
+            plan.files.push(file);
+        }
+        // declined overwrite is removed from the plan before registration
+        plan.files
+            .retain(|file| file.action != TemplatePlanAction::Overwrite);
+        plan.register_files().unwrap();
+
+        assert_eq!(plan.files.len(), 2);
+        assert_eq!(plan.files[1].path, directory.join("mod.rs"));
+        assert_eq!(plan.files[1].content, "pub mod a;\n");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use crate::template_insert::TemplateInsert;
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+
+const RUST_MODULE_FILES: [&str; 3] = ["mod.rs", "lib.rs", "main.rs"];
+const SCRIPT_EXTENSIONS: [&str; 8] = ["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TemplateRegisterLanguage {
+    Rust,
+    /// typescript & javascript
+    Script,
+}
+
+/**
+ * Registration of generated file in its parent module, set per file in template `_.json`:
+ * `"files": { "src/__var__snake__.rs": { "register": true } }`.
+ * Rust files are declared with `pub mod name;` in `mod.rs`, `lib.rs` or `main.rs`,
+ * TS/JS files are exported with `export * from './name';` from `index` file of the folder.
+ */
+#[derive(Debug, Clone)]
+pub struct TemplateRegister {
+    language: TemplateRegisterLanguage,
+    /// file with module declarations, created when it is missing
+    pub module_path: PathBuf,
+    /// module name for rust, module specifier like `./button` for scripts
+    name: String,
+}
+
+impl TemplateRegister {
+    pub fn new(path: &Path) -> Result<TemplateRegister, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let language = match extension {
+            "rs" => TemplateRegisterLanguage::Rust,
+            extension if SCRIPT_EXTENSIONS.contains(&extension) => TemplateRegisterLanguage::Script,
+            _ => {
+                return Err(format!(
+                    "Cannot register {}, only rust, typescript and javascript files are supported",
+                    path.to_str().unwrap()
+                ))
+            }
+        };
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let file_stem = path.file_stem().unwrap().to_str().unwrap();
+        let is_module_file = match language {
+            TemplateRegisterLanguage::Rust => RUST_MODULE_FILES.contains(&file_name),
+            TemplateRegisterLanguage::Script => file_stem == "index",
+        };
+        if is_module_file && file_name != "mod.rs" && language == TemplateRegisterLanguage::Rust {
+            return Err(format!(
+                "Cannot register crate root {}",
+                path.to_str().unwrap()
+            ));
+        }
+
+        // module file registers its folder in the parent folder
+        let (directory, name) = match path.parent() {
+            Some(directory) if is_module_file => (
+                directory.parent().unwrap_or(directory),
+                directory.file_name().unwrap_or_default().to_str().unwrap(),
+            ),
+            Some(directory) => (directory, file_stem),
+            None => (Path::new(""), file_stem),
+        };
+        let name = match language {
+            TemplateRegisterLanguage::Rust => name.to_string(),
+            TemplateRegisterLanguage::Script => format!("./{}", name),
+        };
+        Ok(TemplateRegister {
+            language,
+            module_path: TemplateRegister::module_path(language, directory, extension),
+            name,
+        })
+    }
+
+    /**
+     * Existing module file of the folder, rust also looks for `folder.rs` next to the folder.
+     */
+    fn module_path(
+        language: TemplateRegisterLanguage,
+        directory: &Path,
+        extension: &str,
+    ) -> PathBuf {
+        match language {
+            TemplateRegisterLanguage::Rust => RUST_MODULE_FILES
+                .iter()
+                .map(|file_name| directory.join(file_name))
+                .chain(
+                    directory
+                        .file_name()
+                        .map(|_| directory.with_extension("rs")),
+                )
+                .find(|path| path.is_file())
+                .unwrap_or_else(|| directory.join("mod.rs")),
+            TemplateRegisterLanguage::Script => SCRIPT_EXTENSIONS
+                .iter()
+                .map(|extension| directory.join(format!("index.{}", extension)))
+                .find(|path| path.is_file())
+                .unwrap_or_else(|| {
+                    let is_typescript = extension.ends_with("ts") || extension == "tsx";
+                    directory.join(if is_typescript {
+                        "index.ts"
+                    } else {
+                        "index.js"
+                    })
+                }),
+        }
+    }
+
+    fn regex(&self) -> Regex {
+        match self.language {
+            TemplateRegisterLanguage::Rust => {
+                Regex::new(r"^\s*((?:pub(?:\([^)]*\))?\s+)?)mod\s+([\w#]+)\s*;").unwrap()
+            }
+            TemplateRegisterLanguage::Script => {
+                Regex::new(r#"^\s*export\s.*\bfrom\s+(['"])([^'"]+)['"]\s*(;?)"#).unwrap()
+            }
+        }
+    }
+
+    /**
+     * Declaration in the style of existing one, `pub` and single quotes with semicolon by default.
+     */
+    fn declaration(&self, existing: Option<&Captures>) -> String {
+        let capture = |index: usize, default: &'static str| {
+            existing
+                .and_then(|captures| captures.get(index))
+                .map(|capture| capture.as_str())
+                .unwrap_or(default)
+        };
+        match self.language {
+            TemplateRegisterLanguage::Rust => format!("{}mod {};", capture(1, "pub "), self.name),
+            TemplateRegisterLanguage::Script => format!(
+                "export * from {}{}{}{}",
+                capture(1, "'"),
+                self.name,
+                capture(1, "'"),
+                capture(3, ";")
+            ),
+        }
+    }
+
+    /**
+     * Module file content with the declaration sorted among existing declarations,
+     * content is unchanged when module is already declared.
+     */
+    pub fn register(&self, existing: &str) -> String {
+        let regex = self.regex();
+        let declarations = TemplateInsert::lines(existing)
+            .filter_map(|(start, line)| {
+                regex
+                    .captures(line.trim_end_matches(['\r', '\n']))
+                    .map(|captures| (start, line, captures))
+            })
+            .collect::<Vec<_>>();
+        let key = |captures: &Captures| captures[2].trim_start_matches("r#").to_string();
+        if declarations
+            .iter()
+            .any(|(_, _, captures)| key(captures) == self.name)
+        {
+            return existing.to_string();
+        }
+
+        let declaration = self.declaration(declarations.first().map(|(_, _, captures)| captures));
+        let index = declarations
+            .iter()
+            .find(|(_, _, captures)| key(captures) > self.name)
+            .map(|(start, _, _)| *start)
+            .or_else(|| {
+                declarations
+                    .last()
+                    .map(|(start, line, _)| start + line.len())
+            })
+            // first rust module goes before the code, first export after imports
+            .unwrap_or_else(|| match self.language {
+                TemplateRegisterLanguage::Rust => {
+                    TemplateInsert::top_index(&self.module_path, existing)
+                }
+                TemplateRegisterLanguage::Script => existing.len(),
+            });
+        let fragment = TemplateInsert::fragment(&declaration, existing);
+        TemplateInsert::insert_at(existing, index, &fragment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateRegister;
+    use crate::template_git::TemplateGit;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn should_register_sorted_declarations() {
+        let register = TemplateRegister::new(Path::new("src/components/button.rs")).unwrap();
+        assert_eq!(register.module_path, Path::new("src/components/mod.rs"));
+        assert_eq!(register.register(""), "pub mod button;\n");
+        let existing = "//! components\n\npub mod alert;\npub mod card;\n\npub use card::Card;\n";
+        assert_eq!(
+            register.register(existing),
+            "//! components\n\npub mod alert;\npub mod button;\npub mod card;\n\npub use card::Card;\n"
+        );
+        assert_eq!(register.register("mod alert;"), "mod alert;\nmod button;\n");
+        let existing = "pub mod button;\npub mod card;\n";
+        assert_eq!(register.register(existing), existing);
+
+        let register = TemplateRegister::new(Path::new("src/components/card/index.tsx")).unwrap();
+        assert_eq!(register.module_path, Path::new("src/components/index.ts"));
+        let existing = "export * from \"./alert\"\nexport * from \"./dialog\"\n";
+        assert_eq!(
+            register.register(existing),
+            "export * from \"./alert\"\nexport * from \"./card\"\nexport * from \"./dialog\"\n"
+        );
+        assert_eq!(
+            register.register("import './styles.css';\r\n"),
+            "import './styles.css';\r\nexport * from './card';\r\n"
+        );
+
+        assert!(TemplateRegister::new(Path::new("src/lib.rs")).is_err());
+        assert!(TemplateRegister::new(Path::new("src/styles.css")).is_err());
+    }
+
+    #[test]
+    fn should_find_existing_module_file() {
+        let path = TemplateGit::temp_directory("test-register").unwrap();
+        fs::create_dir_all(path.join("src/components")).unwrap();
+        fs::write(path.join("src/components.rs"), "").unwrap();
+        fs::write(path.join("src/lib.rs"), "").unwrap();
+
+        let register = TemplateRegister::new(&path.join("src/components/button.rs")).unwrap();
+        assert_eq!(register.module_path, path.join("src/components.rs"));
+        let register = TemplateRegister::new(&path.join("src/components/mod.rs")).unwrap();
+        assert_eq!(register.module_path, path.join("src/lib.rs"));
+        assert_eq!(register.register(""), "pub mod components;\n");
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,592 @@
+use crate::{
+    case_util::CaseType,
+    search_folder::SearchFolder,
+    template_case::TemplateCaseResolver,
+    template_variable::{TemplateCondition, TemplateVariable, TemplateVariableInfo},
+};
+use std::collections::HashMap;
+
+/**
+ * State of one `__if__ ... __endif__` block while rendering.
+ */
+struct TemplateBlock {
+    is_parent_active: bool,
+    is_taken: bool,
+    is_active: bool,
+    has_else: bool,
+}
+
+pub struct TemplateRender;
+
+impl TemplateRender {
+    /**
+     * Parse boolean value of `__bool__` variable, like `true`, `yes`, `y`, `1` or `on`.
+     */
+    pub fn parse_bool(value: &str) -> Option<bool> {
+        match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "y" | "1" | "on" => Some(true),
+            "false" | "no" | "n" | "0" | "off" | "" => Some(false),
+            _ => None,
+        }
+    }
+
+    /**
+     * Condition without expected value checks if value is set (or true for bool),
+     * with value it's compared ignoring case and separators, so `withtests` matches `with-tests`.
+     */
+    pub fn is_condition_met(
+        condition: &TemplateCondition,
+        values_for_keys: &HashMap<String, String>,
+    ) -> bool {
+        let key = SearchFolder::get_key(&condition.to_variable_info());
+        let value = values_for_keys.get(&key).map(|value| value.as_str());
+        let value = value.unwrap_or_default();
+
+        if let Some(expected) = &condition.value {
+            if condition.template_variable.is_bool() {
+                return TemplateRender::parse_bool(value) == TemplateRender::parse_bool(expected);
+            }
+            return CaseType::FlatCase.to_case_string(value)
+                == CaseType::FlatCase.to_case_string(expected);
+        }
+        if condition.template_variable.is_bool() {
+            return TemplateRender::parse_bool(value).unwrap_or(false);
+        }
+        !value.trim().is_empty()
+    }
+
+    pub fn has_blocks(content: &str) -> bool {
+        TemplateVariableInfo::parse_iter(content).any(|variable| {
+            variable.template_variable.is_block() || variable.template_variable.is_repeat()
+        })
+    }
+
+    /**
+     * Value of `__list__` variable is stored as comma separated items,
+     * comma inside of item is escaped as `\,` and backslash as `\\`.
+     */
+    pub fn split_list(value: &str) -> Vec<String> {
+        let mut items = Vec::new();
+        let mut item = String::new();
+        let mut chars = value.chars().peekable();
+        while let Some(char) = chars.next() {
+            match (char, chars.peek()) {
+                ('\\', Some(next @ (',' | '\\'))) => {
+                    item.push(*next);
+                    chars.next();
+                }
+                (',', _) => items.push(std::mem::take(&mut item)),
+                _ => item.push(char),
+            }
+        }
+        items.push(item);
+        items
+            .iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| item.to_string())
+            .collect()
+    }
+
+    pub fn join_list(items: &[String]) -> String {
+        items
+            .iter()
+            .map(|item| item.replace('\\', "\\\\").replace(',', "\\,"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /**
+     * List as text in rendered file, items are not escaped there.
+     */
+    pub fn display_list(items: &[String]) -> String {
+        items.join(", ")
+    }
+
+    /**
+     * Values for one item of the list, list variable gets the item and helpers are set.
+     * First and last helpers are empty when not met, so they can be used as conditions.
+     */
+    pub fn get_item_values(
+        values_for_keys: &HashMap<String, String>,
+        list_key: &str,
+        items: &[String],
+        index: usize,
+    ) -> HashMap<String, String> {
+        let mut item_values = values_for_keys.clone();
+        // item is a list with single item, so comma inside of it stays escaped
+        let item = TemplateRender::join_list(&items[index..=index]);
+        item_values.insert(list_key.to_string(), item);
+        let is_first = if index == 0 { "true" } else { "" };
+        let is_last = if index == items.len() - 1 { "true" } else { "" };
+        let helpers = [
+            (TemplateVariable::Index, index.to_string()),
+            (TemplateVariable::First, is_first.to_string()),
+            (TemplateVariable::Last, is_last.to_string()),
+        ];
+        for (helper, value) in helpers {
+            item_values.insert(format!("{}_", helper), value);
+        }
+        item_values
+    }
+
+    /**
+     * List items are cased one by one, so `a b, c d` in kebab case is `a-b, c-d`.
+     */
+    pub fn to_case_value(
+        case_resolver: &TemplateCaseResolver,
+        variable: &TemplateVariableInfo,
+        is_file_path: bool,
+        value: &str,
+    ) -> String {
+        if !variable.template_variable.is_list() {
+            return case_resolver.to_case(variable, is_file_path, value);
+        }
+        let items = TemplateRender::split_list(value)
+            .iter()
+            .map(|item| case_resolver.to_case(variable, is_file_path, item))
+            .collect::<Vec<_>>();
+        TemplateRender::display_list(&items)
+    }
+
+    /**
+     * Render template content in one pass over its tokens. Only active branches of
+     * `__if__` / `__elif__` / `__else__` / `__endif__` blocks are kept, content of
+     * `__each__name__ ... __endeach__` is rendered once per item of `__list__name__`
+     * and variables are replaced with their cased values.
+     * Rendered values are never parsed again, variables without value are left as they are.
+     * Block token which is alone on its line is removed together with the line.
+     */
+    pub fn render(
+        content: &str,
+        values_for_keys: &HashMap<String, String>,
+        case_resolver: &TemplateCaseResolver,
+    ) -> Result<String, String> {
+        TemplateRender::render_part(content, 0, values_for_keys, case_resolver)
+    }
+
+    /**
+     * Render part of the template, lines of errors are counted from `line_offset`.
+     */
+    fn render_part(
+        content: &str,
+        line_offset: usize,
+        values_for_keys: &HashMap<String, String>,
+        case_resolver: &TemplateCaseResolver,
+    ) -> Result<String, String> {
+        let mut output = String::new();
+        let mut blocks: Vec<TemplateBlock> = Vec::new();
+        let mut each = None;
+        let mut depth = 0;
+        let mut last_index = 0;
+
+        for variable in TemplateVariableInfo::parse_iter(content) {
+            let template_variable = variable.template_variable;
+            let line = line_offset + content[..variable.start_index].matches('\n').count() + 1;
+            let (start, end) =
+                TemplateRender::token_range(content, variable.start_index, variable.end_index);
+
+            // repeated content is rendered with every item once its `__endeach__` is found
+            if depth > 0 {
+                match template_variable {
+                    TemplateVariable::Each => depth += 1,
+                    TemplateVariable::EndEach => depth -= 1,
+                    _ => {}
+                }
+                if depth > 0 {
+                    continue;
+                }
+                let (each_variable, body_start, is_active) = each.take().unwrap();
+                if is_active {
+                    let body_line_offset =
+                        line_offset + content[..body_start].matches('\n').count();
+                    output.push_str(&TemplateRender::repeat(
+                        &each_variable,
+                        &content[body_start..start],
+                        body_line_offset,
+                        values_for_keys,
+                        case_resolver,
+                    )?);
+                }
+                last_index = end;
+                continue;
+            }
+
+            let is_active = blocks.last().map(|block| block.is_active).unwrap_or(true);
+            if !template_variable.is_block() && !template_variable.is_repeat() {
+                if !is_active {
+                    continue;
+                }
+                let (start_index, end_index) = (variable.start_index, variable.end_index);
+                let value = TemplateRender::render_variable(
+                    variable,
+                    values_for_keys,
+                    case_resolver,
+                    false,
+                );
+                if let Some(value) = value {
+                    output.push_str(&content[last_index..start_index]);
+                    output.push_str(&value);
+                    last_index = end_index;
+                }
+                continue;
+            }
+
+            let error = |message: &str| {
+                Err(format!(
+                    "{} {} on line {}",
+                    message, variable.raw_value, line
+                ))
+            };
+            if template_variable == TemplateVariable::EndEach {
+                return error("Missing __each__ before");
+            }
+            if is_active {
+                output.push_str(&content[last_index..start]);
+            }
+            last_index = end;
+            if template_variable == TemplateVariable::Each {
+                depth = 1;
+                each = Some((variable, end, is_active));
+                continue;
+            }
+
+            let is_met = |variable: &TemplateVariableInfo| match &variable.condition {
+                Some(condition) => Ok(TemplateRender::is_condition_met(condition, values_for_keys)),
+                None => Err(()),
+            };
+
+            match template_variable {
+                TemplateVariable::If => {
+                    let is_met = is_met(&variable);
+                    if is_met.is_err() {
+                        return error("Invalid condition");
+                    }
+                    let is_met = is_active && is_met.unwrap();
+                    blocks.push(TemplateBlock {
+                        is_parent_active: is_active,
+                        is_taken: is_met,
+                        is_active: is_met,
+                        has_else: false,
+                    });
+                }
+                TemplateVariable::Elif => {
+                    let is_met = is_met(&variable);
+                    if is_met.is_err() {
+                        return error("Invalid condition");
+                    }
+                    let block = blocks.last_mut();
+                    if block.is_none() {
+                        return error("Missing __if__ before");
+                    }
+                    let block = block.unwrap();
+                    if block.has_else {
+                        return error("Unexpected");
+                    }
+                    let is_met = block.is_parent_active && !block.is_taken && is_met.unwrap();
+                    block.is_active = is_met;
+                    block.is_taken = block.is_taken || is_met;
+                }
+                TemplateVariable::Else => {
+                    let block = blocks.last_mut();
+                    if block.is_none() {
+                        return error("Missing __if__ before");
+                    }
+                    let block = block.unwrap();
+                    if block.has_else {
+                        return error("Duplicate");
+                    }
+                    block.is_active = block.is_parent_active && !block.is_taken;
+                    block.is_taken = true;
+                    block.has_else = true;
+                }
+                _ => {
+                    if blocks.pop().is_none() {
+                        return error("Missing __if__ before");
+                    }
+                }
+            }
+        }
+
+        if depth > 0 {
+            return Err("Missing __endeach__ at the end of template".to_string());
+        }
+        if !blocks.is_empty() {
+            return Err("Missing __endif__ at the end of template".to_string());
+        }
+        output.push_str(&content[last_index..]);
+        Ok(output)
+    }
+
+    /**
+     * Content of `__each__` block for every item, the list variable is the current item
+     * and `__index__`, `__if__first__` and `__if__last__` helpers can be used.
+     */
+    fn repeat(
+        each_variable: &TemplateVariableInfo,
+        body: &str,
+        line_offset: usize,
+        values_for_keys: &HashMap<String, String>,
+        case_resolver: &TemplateCaseResolver,
+    ) -> Result<String, String> {
+        let list = each_variable.condition.as_ref().unwrap().to_variable_info();
+        let list_key = SearchFolder::get_key(&list);
+        let value = values_for_keys.get(&list_key).map(|value| value.as_str());
+        let items = TemplateRender::split_list(value.unwrap_or_default());
+
+        let mut output = String::new();
+        for index in 0..items.len() {
+            let item_values =
+                TemplateRender::get_item_values(values_for_keys, &list_key, &items, index);
+            output.push_str(&TemplateRender::render_part(
+                body,
+                line_offset,
+                &item_values,
+                case_resolver,
+            )?);
+        }
+        Ok(output)
+    }
+
+    /**
+     * Cased value of the variable, None when the variable has no value.
+     */
+    fn render_variable(
+        variable: TemplateVariableInfo,
+        values_for_keys: &HashMap<String, String>,
+        case_resolver: &TemplateCaseResolver,
+        is_file_path: bool,
+    ) -> Option<String> {
+        let variable =
+            SearchFolder::resolve_name(variable, |key| values_for_keys.contains_key(key));
+        let value = values_for_keys.get(&SearchFolder::get_key(&variable))?;
+        Some(TemplateRender::to_case_value(
+            case_resolver,
+            &variable,
+            is_file_path,
+            value,
+        ))
+    }
+
+    /**
+     * Replace variables of file path part with their cased values in one pass,
+     * so values are never replaced again and every occurrence gets its own case.
+     * Variables without value are left as they are.
+     */
+    pub fn render_variables(
+        content: &str,
+        values_for_keys: &HashMap<String, String>,
+        case_resolver: &TemplateCaseResolver,
+        is_file_path: bool,
+    ) -> String {
+        let mut output = String::new();
+        let mut last_index = 0;
+        for variable in TemplateVariableInfo::parse_iter(content) {
+            let (start_index, end_index) = (variable.start_index, variable.end_index);
+            let value = TemplateRender::render_variable(
+                variable,
+                values_for_keys,
+                case_resolver,
+                is_file_path,
+            );
+            if let Some(value) = value {
+                output.push_str(&content[last_index..start_index]);
+                output.push_str(&value);
+                last_index = end_index;
+            }
+        }
+        output.push_str(&content[last_index..]);
+        output
+    }
+
+    /**
+     * Range of block token, extended to the whole line when there is nothing else on it.
+     */
+    fn token_range(content: &str, start: usize, end: usize) -> (usize, usize) {
+        let line_start = content[..start].rfind('\n').map(|index| index + 1);
+        let line_start = line_start.unwrap_or(0);
+        let line_end = content[end..].find('\n').map(|index| end + index + 1);
+        let line_end = line_end.unwrap_or(content.len());
+
+        if content[line_start..start].trim().is_empty() && content[end..line_end].trim().is_empty()
+        {
+            return (line_start, line_end);
+        }
+        (start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TemplateRender;
+    use crate::{
+        config::ConfigFile, template::TemplateConfig, template_case::TemplateCaseResolver,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn should_render_blocks() {
+        let case_resolver =
+            TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        let mut values = HashMap::new();
+        values.insert("__select___kind".to_string(), "With Tests".to_string());
+        values.insert("__bool___stories".to_string(), "false".to_string());
+
+        let content = "start\n\
+            __if__select__kind__basic__\n\
+            basic __var__name__\n\
+            __elif__select__kind__withtests__\n\
+            tests\n\
+            __if__bool__stories__\n\
+            stories\n\
+            __else__\n\
+            no stories\n\
+            __endif__\n\
+            __else__\n\
+            other\n\
+            __endif__\n\
+            end\n";
+        let result = TemplateRender::render(content, &values, &case_resolver).unwrap();
+        assert_eq!(result, "start\ntests\nno stories\nend\n");
+
+        values.insert("__bool___stories".to_string(), "yes".to_string());
+        let result = TemplateRender::render(content, &values, &case_resolver).unwrap();
+        assert_eq!(result, "start\ntests\nstories\nend\n");
+
+        values.insert("__select___kind".to_string(), "basic".to_string());
+        let result = TemplateRender::render(content, &values, &case_resolver).unwrap();
+        assert_eq!(result, "start\nbasic __var__name__\nend\n");
+    }
+
+    #[test]
+    fn should_report_invalid_blocks() {
+        let case_resolver =
+            TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        let values = HashMap::new();
+        let result = TemplateRender::render("__if__bool__a__\nx\n", &values, &case_resolver);
+        assert!(result.is_err());
+
+        let result = TemplateRender::render("x\n__endif__\n", &values, &case_resolver);
+        assert_eq!(
+            result.unwrap_err(),
+            "Missing __if__ before __endif__ on line 2"
+        );
+
+        let result = TemplateRender::render("__if__name__ x __endif__", &values, &case_resolver);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_repeat_each_blocks() {
+        let case_resolver =
+            TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        let mut values = HashMap::new();
+        values.insert("__list___fields".to_string(), "user id, name".to_string());
+        values.insert("__var___name".to_string(), "user".to_string());
+
+        let content = "struct __var__name__ {\n\
+            __each__fields__\n\
+            \x20   __list__fields__snake__: String, // __index____if__first__ first__endif____if__last__ last__endif__\n\
+            __endeach__\n\
+            }\n";
+        let result = TemplateRender::render(content, &values, &case_resolver).unwrap();
+        assert_eq!(
+            result,
+            "struct User {\n    user_id: String, // 0 first\n    name: String, // 1 last\n}\n"
+        );
+
+        let result = TemplateRender::render("__endeach__", &values, &case_resolver);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_keep_escaped_commas_in_list_items() {
+        let case_resolver =
+            TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        assert_eq!(
+            TemplateRender::split_list("a\\, b, c\\d, e\\\\, f"),
+            ["a, b", "c\\d", "e\\", "f"]
+        );
+        let items = ["a, b".to_string(), "c\\".to_string()];
+        assert_eq!(
+            TemplateRender::split_list(&TemplateRender::join_list(&items)),
+            items
+        );
+
+        let mut values = HashMap::new();
+        values.insert(
+            "__list___items".to_string(),
+            TemplateRender::join_list(&items),
+        );
+        let content = "__each__items__\n- __list__items__snake____if__first__ first__endif__\n__endeach__\n__list__items__kebab__\n";
+        let result = TemplateRender::render(content, &values, &case_resolver).unwrap();
+        assert_eq!(result, "- a,_b first\n- c\\\na,-b, c\\\n");
+    }
+
+    #[test]
+    fn should_not_render_tokens_in_list_items() {
+        let case_resolver =
+            TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        let mut values = HashMap::new();
+        values.insert(
+            "__list___items".to_string(),
+            "__var__name__, __index__".to_string(),
+        );
+        values.insert("__var___name".to_string(), "user".to_string());
+
+        let content = "__var__name__:\n__each__items__\n- __list__items__\n__endeach__\n";
+        let result = TemplateRender::render(content, &values, &case_resolver).unwrap();
+        // items are cased as text, tokens in them are not rendered again
+        assert_eq!(result, "User:\n- VarName\n- Index\n");
+
+        let result = TemplateRender::render(
+            "__each__items__\nx\n__endeach__\n__endeach__\n",
+            &values,
+            &case_resolver,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            "Missing __each__ before __endeach__ on line 4"
+        );
+    }
+
+    #[test]
+    fn should_render_variables_in_single_pass() {
+        let case_resolver =
+            TemplateCaseResolver::new(&TemplateConfig::new(), None, &ConfigFile::new());
+        let mut values = HashMap::new();
+        values.insert("__var___".to_string(), "user card".to_string());
+        values.insert("__select___".to_string(), "primary".to_string());
+        values.insert("__var___title".to_string(), "Home Page".to_string());
+
+        // same variable with different case in each occurrence, unknown variables stay as they are
+        let result = TemplateRender::render_variables(
+            "__var__kebab__ __var__title__kebab__ __var__title__snake__ __var__title__kebab__ __bool__x__",
+            &values,
+            &case_resolver,
+            false,
+        );
+        assert_eq!(
+            result,
+            "user-card home-page home_page home-page __bool__x__"
+        );
+
+        // every variable of path segment is rendered
+        let result = TemplateRender::render_variables(
+            "__var__kebab__-__select__snake__.ts",
+            &values,
+            &case_resolver,
+            true,
+        );
+        assert_eq!(result, "user-card-primary.ts");
+
+        // `__var__title__` is variable `title` when it has value, otherwise title case
+        let result =
+            TemplateRender::render_variables("__var__title__", &values, &case_resolver, false);
+        assert_eq!(result, "HomePage");
+        values.remove("__var___title");
+        let result =
+            TemplateRender::render_variables("__var__title__", &values, &case_resolver, false);
+        assert_eq!(result, "User Card");
+    }
+}
@@ -0,0 +1,640 @@
+use crate::{
+    config::Config,
+    constants::{CONFIG_FILE, TEMPLATE_ROOT_FOLDER},
+    template::TemplateFolder,
+    template_git::TemplateGit,
+};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek},
+    path::{Component, Path, PathBuf},
+};
+
+/**
+ * Where `gen fetch` takes templates from.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// git url, or path to git repository
+    Git(String),
+    /// project root or its `.gen` folder
+    Directory(PathBuf),
+    /// `.tar.gz`, `.tgz` or `.zip` file
+    Archive(PathBuf),
+    /// `.tar.gz`, `.tgz` or `.zip` file served over http(s)
+    ArchiveUrl(String),
+}
+
+/**
+ * Fetched source, `root` is the downloaded / extracted folder, or local directory itself.
+ */
+#[derive(Debug, Clone)]
+pub struct FetchedSource {
+    pub root: PathBuf,
+    /// resolved commit sha, only for git sources
+    pub commit: Option<String>,
+    /// name used for single template in the source root, e.g. repository name
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+impl TemplateSource {
+    pub fn parse(source: &str) -> TemplateSource {
+        let source = source.trim();
+        let is_url = source.starts_with("http://") || source.starts_with("https://");
+        if is_url {
+            if TemplateSource::archive_kind(source).is_some() {
+                return TemplateSource::ArchiveUrl(source.to_string());
+            }
+            return TemplateSource::Git(source.to_string());
+        }
+
+        let path = Path::new(source);
+        if path.is_file() && TemplateSource::archive_kind(source).is_some() {
+            return TemplateSource::Archive(path.to_path_buf());
+        }
+        // bare repositories are fetched with git, working copies are read as they are
+        let is_bare_repository = path.join("HEAD").is_file() && path.join("objects").is_dir();
+        if path.is_dir() && !is_bare_repository {
+            return TemplateSource::Directory(path.to_path_buf());
+        }
+        TemplateSource::Git(source.to_string())
+    }
+
+    /**
+     * Source recorded in gen.lock, local paths are absolute, so update works from any directory.
+     * Urls are kept as they are.
+     */
+    pub fn lock_source(&self) -> String {
+        let path = match self {
+            TemplateSource::Directory(path) | TemplateSource::Archive(path) => path.to_path_buf(),
+            // local repository path, not url
+            TemplateSource::Git(source) if Path::new(source).exists() => PathBuf::from(source),
+            TemplateSource::Git(url) | TemplateSource::ArchiveUrl(url) => return url.to_owned(),
+        };
+        fs::canonicalize(&path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /**
+     * Split `source#subdir` to source and subdirectory.
+     */
+    pub fn split_subdir(source: &str) -> (&str, Option<&str>) {
+        match source.trim().split_once('#') {
+            Some((source, subdir)) if !subdir.is_empty() => (source, Some(subdir)),
+            Some((source, _)) => (source, None),
+            None => (source.trim(), None),
+        }
+    }
+
+    /**
+     * Subdirectory is joined to fetched source, so like template name it can never point
+     * outside of it, `..` and absolute paths are rejected.
+     */
+    pub fn validate_subdir(subdir: &str) -> Result<(), String> {
+        let is_valid = Path::new(subdir)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !is_valid {
+            return Err(format!(
+                "Invalid subdirectory '{}', expected relative path inside of the source",
+                subdir
+            ));
+        }
+        Ok(())
+    }
+
+    /**
+     * Name of the source without extension, e.g. `templates` for `acme/templates.git`.
+     */
+    pub fn name(&self) -> String {
+        let source = match self {
+            TemplateSource::Git(url) | TemplateSource::ArchiveUrl(url) => url.to_owned(),
+            TemplateSource::Directory(path) | TemplateSource::Archive(path) => {
+                path.to_string_lossy().to_string()
+            }
+        };
+        let source = source.split(['?', '#']).next().unwrap_or_default();
+        let name = source
+            .trim_end_matches('/')
+            .rsplit(['/', '\\', ':'])
+            .next()
+            .unwrap_or_default();
+        [".git", ".tar.gz", ".tgz", ".zip"]
+            .iter()
+            .fold(name, |name, extension| {
+                name.strip_suffix(extension).unwrap_or(name)
+            })
+            .to_string()
+    }
+
+    /**
+     * Namespace for templates which collide with existing ones, owner of remote repository,
+     * e.g. `acme` for `https://github.com/acme/templates.git`, or name of local source.
+     */
+    pub fn namespace(&self) -> String {
+        let namespace = match self {
+            TemplateSource::Git(url) | TemplateSource::ArchiveUrl(url) => {
+                let url = url.split(['?', '#']).next().unwrap_or_default();
+                let is_remote = (url.contains("://") && !url.starts_with("file://"))
+                    || url
+                        .split_once(':')
+                        .is_some_and(|(host, _)| host.contains('@'));
+                let segments = url
+                    .split_once("://")
+                    .map(|(_, path)| path)
+                    .unwrap_or(url)
+                    .split(['/', ':'])
+                    .filter(|segment| !segment.is_empty())
+                    .collect::<Vec<_>>();
+                // host, owner and repository name
+                if is_remote && segments.len() >= 3 {
+                    segments[segments.len() - 2].to_string()
+                } else {
+                    self.name()
+                }
+            }
+            TemplateSource::Directory(path) | TemplateSource::Archive(path) => {
+                let path = if path.ends_with(TEMPLATE_ROOT_FOLDER) {
+                    path.parent().unwrap_or(path)
+                } else {
+                    path
+                };
+                TemplateSource::Directory(path.to_path_buf()).name()
+            }
+        };
+        let namespace = namespace
+            .trim_start_matches('.')
+            .replace(char::is_whitespace, "-");
+        if namespace.is_empty() {
+            return "fetched".to_string();
+        }
+        namespace
+    }
+
+    /**
+     * Remote sources are downloaded to `temp_path`, local directory is used as it is.
+     */
+    pub fn fetch(&self, git_ref: Option<&str>, temp_path: &Path) -> Result<FetchedSource, String> {
+        // ref of local working copy is read from its git history
+        if let (Some(_), TemplateSource::Directory(path)) = (git_ref, self) {
+            if path.join(".git").exists() {
+                let source = TemplateSource::Git(path.to_string_lossy().to_string());
+                return source.fetch(git_ref, temp_path);
+            }
+        }
+        if git_ref.is_some() && !matches!(self, TemplateSource::Git(_)) {
+            return Err("--ref can be used only with git sources".to_string());
+        }
+        match self {
+            TemplateSource::Git(url) => {
+                let commit = TemplateGit::clone(url, git_ref, temp_path)?;
+                Ok(FetchedSource {
+                    root: temp_path.to_path_buf(),
+                    commit: Some(commit.to_string()),
+                    name: self.name(),
+                })
+            }
+            TemplateSource::Directory(path) => Ok(self.fetched(path)),
+            TemplateSource::Archive(path) => {
+                let kind = TemplateSource::archive_kind(&path.to_string_lossy()).unwrap();
+                let file = File::open(path)
+                    .map_err(|error| format!("Cannot open {}: {}", path.display(), error))?;
+                TemplateSource::extract(file, kind, temp_path)?;
+                Ok(self.fetched(temp_path))
+            }
+            TemplateSource::ArchiveUrl(url) => {
+                let kind = TemplateSource::archive_kind(url).unwrap();
+                fs::create_dir_all(temp_path).map_err(|error| error.to_string())?;
+                let file = TemplateSource::download(url, &temp_path.join("download"))?;
+                let extract_path = temp_path.join("archive");
+                TemplateSource::extract(file, kind, &extract_path)?;
+                Ok(self.fetched(&extract_path))
+            }
+        }
+    }
+
+    fn fetched(&self, root: &Path) -> FetchedSource {
+        FetchedSource {
+            root: root.to_path_buf(),
+            commit: None,
+            name: self.name(),
+        }
+    }
+
+    fn archive_kind(source: &str) -> Option<ArchiveKind> {
+        // query and fragment of url are not part of file name
+        let name = source.split(['?', '#']).next().unwrap_or(source);
+        let name = name.to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Some(ArchiveKind::TarGz);
+        }
+        if name.ends_with(".zip") {
+            return Some(ArchiveKind::Zip);
+        }
+        None
+    }
+
+    fn extract<R: Read + Seek>(
+        reader: R,
+        kind: ArchiveKind,
+        destination: &Path,
+    ) -> Result<(), String> {
+        fs::create_dir_all(destination).map_err(|error| error.to_string())?;
+        // both unpackers skip entries pointing outside of destination
+        let result = match kind {
+            ArchiveKind::TarGz => tar::Archive::new(GzDecoder::new(reader))
+                .unpack(destination)
+                .map_err(|error| error.to_string()),
+            ArchiveKind::Zip => zip::ZipArchive::new(reader)
+                .and_then(|mut archive| archive.extract(destination))
+                .map_err(|error| error.to_string()),
+        };
+        result.map_err(|error| format!("Cannot extract archive: {}", error))
+    }
+
+    fn download(url: &str, destination: &Path) -> Result<File, String> {
+        let response = ureq::get(url).call().map_err(|error| match error {
+            ureq::Error::Status(status, _) => format!("Cannot download {}: HTTP {}", url, status),
+            ureq::Error::Transport(transport) => {
+                format!("Cannot download {}: {}", url, transport)
+            }
+        })?;
+        let mut file = File::options()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(destination)
+            .map_err(|error| format!("Cannot create {}: {}", destination.display(), error))?;
+        io::copy(&mut response.into_reader(), &mut file)
+            .and_then(|_| file.rewind())
+            .map_err(|error| format!("Cannot download {}: {}", url, error))?;
+        Ok(file)
+    }
+}
+
+impl FetchedSource {
+    /**
+     * Templates of the source, or of its `subdir`. Archive with single top level folder
+     * (e.g. `repo-main/`) is searched inside of that folder too.
+     */
+    pub fn templates(&self, subdir: Option<&str>) -> Result<Vec<TemplateFolder>, String> {
+        let mut path = self.root.to_path_buf();
+        loop {
+            let subdir_path = subdir.map_or(path.to_path_buf(), |subdir| path.join(subdir));
+            if let Some(templates) = self.find_templates(&subdir_path)? {
+                return Ok(templates);
+            }
+            match FetchedSource::single_folder(&path) {
+                Some(folder) => path = folder,
+                None => break,
+            }
+        }
+        Err(match subdir {
+            Some(subdir) => format!("{} does not contain templates", subdir),
+            None => format!(
+                "Source does not contain {} template folder",
+                TEMPLATE_ROOT_FOLDER
+            ),
+        })
+    }
+
+    /**
+     * Templates are in `.gen` folder of project root, or directly in given folder when it
+     * is `.gen` itself or contains template folders. Folder with `_.json` and without `.gen`
+     * is a single template.
+     */
+    fn find_templates(&self, path: &Path) -> Result<Option<Vec<TemplateFolder>>, String> {
+        if !path.is_dir() {
+            return Ok(None);
+        }
+        let template_root = path.join(TEMPLATE_ROOT_FOLDER);
+        if template_root.is_dir() {
+            return Ok(Some(FetchedSource::template_folders(&template_root)));
+        }
+        if path.join(CONFIG_FILE).is_file() {
+            return Ok(Some(vec![TemplateFolder {
+                name: self.template_name(path),
+                path: path.to_path_buf(),
+            }]));
+        }
+        let template_folders = FetchedSource::template_folders(path);
+        let is_template_root = path
+            .file_name()
+            .is_some_and(|name| name == TEMPLATE_ROOT_FOLDER);
+        let has_templates = template_folders
+            .iter()
+            .any(|template_folder| template_folder.path.join(CONFIG_FILE).is_file());
+        if is_template_root || has_templates {
+            return Ok(Some(template_folders));
+        }
+        Ok(None)
+    }
+
+    fn template_folders(path: &Path) -> Vec<TemplateFolder> {
+        Config::load_template_folders(path).template_folders
+    }
+
+    /**
+     * Name from template `_.json`, then folder name, source name for the source root.
+     * Invalid name from `_.json`, like `../src`, is ignored.
+     */
+    fn template_name(&self, path: &Path) -> String {
+        let name = fs::read_to_string(path.join(CONFIG_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|config| config["name"].as_str().map(|name| name.to_string()))
+            .filter(|name| !name.is_empty());
+        match name {
+            Some(name) if TemplateFolder::validate_name(&name).is_ok() => return name,
+            Some(name) => println!(
+                "{} {}",
+                "🚨 Invalid template name in _.json, using folder name instead:".yellow(),
+                name
+            ),
+            None => {}
+        }
+        if path == self.root {
+            return self.name.to_owned();
+        }
+        path.file_name().unwrap().to_string_lossy().to_string()
+    }
+
+    fn single_folder(path: &Path) -> Option<PathBuf> {
+        let entries = fs::read_dir(path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        match entries.as_slice() {
+            [entry] if entry.is_dir() => Some(entry.to_path_buf()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FetchedSource, TemplateSource};
+    use crate::template_git::TemplateGit;
+    use flate2::{write::GzEncoder, Compression};
+    use std::{
+        fs::{self, File},
+        io::{Read, Write},
+        net::TcpListener,
+        path::{Path, PathBuf},
+        thread,
+    };
+
+    fn create_project(path: &Path) {
+        fs::create_dir_all(path.join(".gen/component")).unwrap();
+        fs::write(path.join(".gen/component/_.json"), "{}").unwrap();
+        fs::write(path.join(".gen/component/__var__.ts"), "__var__").unwrap();
+    }
+
+    fn create_tar_gz(source: &Path, prefix: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        builder.append_dir_all(prefix, source).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn should_parse_sources() {
+        let path = TemplateGit::temp_directory("test-source-parse").unwrap();
+        create_project(&path.join("project"));
+        let archive = path.join("templates.tar.gz");
+        fs::write(&archive, "").unwrap();
+
+        assert_eq!(
+            TemplateSource::parse(path.join("project").to_str().unwrap()),
+            TemplateSource::Directory(path.join("project"))
+        );
+        assert_eq!(
+            TemplateSource::parse(archive.to_str().unwrap()),
+            TemplateSource::Archive(archive)
+        );
+        assert_eq!(
+            TemplateSource::parse("https://example.com/templates.zip?token=1"),
+            TemplateSource::ArchiveUrl("https://example.com/templates.zip?token=1".to_string())
+        );
+        assert_eq!(
+            TemplateSource::parse("https://github.com/samuelgja/gen"),
+            TemplateSource::Git("https://github.com/samuelgja/gen".to_string())
+        );
+        assert_eq!(
+            TemplateSource::parse("git@github.com:samuelgja/gen.git"),
+            TemplateSource::Git("git@github.com:samuelgja/gen.git".to_string())
+        );
+
+        let namespaces = [
+            "https://github.com/acme/templates.git",
+            "git@github.com:acme/templates.git",
+            "https://example.com/acme/templates.zip",
+            "file:///srv/git/acme.git",
+        ]
+        .map(|source| TemplateSource::parse(source).namespace());
+        assert_eq!(namespaces, ["acme", "acme", "acme", "acme"]);
+        assert_eq!(
+            TemplateSource::Directory(path.join("project").join(".gen")).namespace(),
+            "project"
+        );
+
+        // local paths are recorded absolute, urls as they are
+        let relative = path.join("project").join("..").join("project");
+        let lock_source = TemplateSource::parse(relative.to_str().unwrap()).lock_source();
+        assert_eq!(
+            lock_source,
+            fs::canonicalize(path.join("project"))
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+        let source = TemplateSource::parse("git@github.com:samuelgja/gen.git");
+        assert_eq!(source.lock_source(), "git@github.com:samuelgja/gen.git");
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    fn template_paths(fetched_source: &FetchedSource, subdir: Option<&str>) -> Vec<PathBuf> {
+        fetched_source
+            .templates(subdir)
+            .unwrap()
+            .into_iter()
+            .map(|template_folder| template_folder.path)
+            .collect()
+    }
+
+    #[test]
+    fn should_fetch_directory_and_archives() {
+        let path = TemplateGit::temp_directory("test-source-fetch").unwrap();
+        let project = path.join("project");
+        create_project(&project);
+
+        let source = TemplateSource::parse(project.to_str().unwrap());
+        let fetched_source = source.fetch(None, &path.join("unused")).unwrap();
+        assert_eq!(
+            template_paths(&fetched_source, None),
+            vec![project.join(".gen/component")]
+        );
+        let source = TemplateSource::parse(project.join(".gen").to_str().unwrap());
+        let fetched_source = source.fetch(None, &path.join("unused")).unwrap();
+        assert_eq!(
+            template_paths(&fetched_source, None),
+            vec![project.join(".gen/component")]
+        );
+        assert!(source.fetch(Some("main"), &path.join("unused")).is_err());
+
+        let archive = path.join("templates.tgz");
+        fs::write(&archive, create_tar_gz(&project, "project-main")).unwrap();
+        let fetched_source = TemplateSource::parse(archive.to_str().unwrap())
+            .fetch(None, &path.join("tar"))
+            .unwrap();
+        assert_eq!(
+            template_paths(&fetched_source, None),
+            vec![path.join("tar/project-main/.gen/component")]
+        );
+
+        let archive = path.join("templates.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::FileOptions::default();
+        writer.start_file("component/_.json", options).unwrap();
+        writer.write_all(b"{}").unwrap();
+        writer.start_file("component/__var__.ts", options).unwrap();
+        writer.write_all(b"__var__").unwrap();
+        writer.finish().unwrap();
+        let fetched_source = TemplateSource::parse(archive.to_str().unwrap())
+            .fetch(None, &path.join("zip"))
+            .unwrap();
+        let template_paths = template_paths(&fetched_source, None);
+        assert_eq!(template_paths, vec![path.join("zip/component")]);
+        assert!(template_paths[0].join("__var__.ts").is_file());
+
+        let fetched_source = TemplateSource::Directory(path.join("tar")).fetch(None, &path);
+        assert!(fetched_source.unwrap().templates(Some("nope")).is_err());
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn should_find_templates_in_subdir() {
+        let path = TemplateGit::temp_directory("test-source-subdir").unwrap();
+        let monorepo = path.join("monorepo");
+        create_project(&monorepo.join("tools"));
+        fs::create_dir_all(monorepo.join("tools/templates/button")).unwrap();
+        fs::write(
+            monorepo.join("tools/templates/button/_.json"),
+            r#"{ "name": "ui-button" }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            TemplateSource::split_subdir("https://x.com/acme/repo.git#tools/templates"),
+            ("https://x.com/acme/repo.git", Some("tools/templates"))
+        );
+        assert_eq!(TemplateSource::split_subdir("./repo#"), ("./repo", None));
+        assert!(TemplateSource::validate_subdir("tools/templates").is_ok());
+        assert!(TemplateSource::validate_subdir("./.gen/button").is_ok());
+        for subdir in ["..", "tools/../../etc", "/etc", "tools/../.."] {
+            assert!(
+                TemplateSource::validate_subdir(subdir).is_err(),
+                "{}",
+                subdir
+            );
+        }
+
+        let fetched_source = TemplateSource::parse(monorepo.to_str().unwrap())
+            .fetch(None, &path.join("unused"))
+            .unwrap();
+        assert_eq!(
+            template_paths(&fetched_source, Some("tools")),
+            vec![monorepo.join("tools/.gen/component")]
+        );
+        assert!(fetched_source.templates(Some("tools/nope")).is_err());
+
+        let templates = fetched_source.templates(Some("tools/templates")).unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].path, monorepo.join("tools/templates/button"));
+
+        // single template, name is taken from its _.json
+        let templates = fetched_source
+            .templates(Some("tools/templates/button"))
+            .unwrap();
+        assert_eq!(templates[0].name, "ui-button");
+
+        // name pointing outside of templates folder falls back to folder name
+        for name in ["../src", "/tmp/src", "acme/.."] {
+            fs::write(
+                monorepo.join("tools/templates/button/_.json"),
+                format!(r#"{{ "name": "{}" }}"#, name),
+            )
+            .unwrap();
+            let templates = fetched_source
+                .templates(Some("tools/templates/button"))
+                .unwrap();
+            assert_eq!(templates[0].name, "button");
+        }
+
+        // single template in source root is named by the source
+        let source = TemplateSource::Git("https://x.com/acme/button-template.git".to_string());
+        assert_eq!(source.name(), "button-template");
+        let fetched_source = FetchedSource {
+            root: monorepo.join(".gen/component"),
+            commit: None,
+            name: source.name(),
+        };
+        create_project(&monorepo);
+        let templates = fetched_source.templates(None).unwrap();
+        assert_eq!(templates[0].name, "button-template");
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn should_fetch_archive_url() {
+        let path = TemplateGit::temp_directory("test-source-url").unwrap();
+        let project = path.join("project");
+        create_project(&project);
+        let body = create_tar_gz(&project, "project");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            for status in ["200 OK", "404 Not Found"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                let body = if status == "200 OK" { &body[..] } else { &[] };
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        let url = format!("http://{}/templates.tar.gz", address);
+        let fetched_source = TemplateSource::parse(&url)
+            .fetch(None, &path.join("download"))
+            .unwrap();
+        let template_paths = template_paths(&fetched_source, None);
+        assert!(template_paths[0].join("__var__.ts").is_file());
+
+        let result = TemplateSource::parse(&url).fetch(None, &path.join("missing"));
+        assert_eq!(
+            result.unwrap_err(),
+            format!("Cannot download {}: HTTP 404", url)
+        );
+        server.join().unwrap();
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -4,6 +4,17 @@ use crate::{case_util::CaseType, constants::TEMPLATE_VARIABLE_REGEX};
 pub enum TemplateVariable {
     Var,
     Select,
+    Bool,
+    List,
+    If,
+    Elif,
+    Else,
+    EndIf,
+    Each,
+    EndEach,
+    Index,
+    First,
+    Last,
 }
 
 impl core::fmt::Display for TemplateVariable {
@@ -11,6 +22,17 @@ impl core::fmt::Display for TemplateVariable {
         match self {
             TemplateVariable::Var => write!(f, "__var__"),
             TemplateVariable::Select => write!(f, "__select__"),
+            TemplateVariable::Bool => write!(f, "__bool__"),
+            TemplateVariable::List => write!(f, "__list__"),
+            TemplateVariable::If => write!(f, "__if__"),
+            TemplateVariable::Elif => write!(f, "__elif__"),
+            TemplateVariable::Else => write!(f, "__else__"),
+            TemplateVariable::EndIf => write!(f, "__endif__"),
+            TemplateVariable::Each => write!(f, "__each__"),
+            TemplateVariable::EndEach => write!(f, "__endeach__"),
+            TemplateVariable::Index => write!(f, "__index__"),
+            TemplateVariable::First => write!(f, "__first__"),
+            TemplateVariable::Last => write!(f, "__last__"),
         }
     }
 }
@@ -24,15 +46,111 @@ impl TemplateVariable {
         self == &TemplateVariable::Select
     }
 
+    pub fn is_bool(&self) -> bool {
+        self == &TemplateVariable::Bool
+    }
+
+    pub fn is_list(&self) -> bool {
+        self == &TemplateVariable::List
+    }
+
+    /**
+     * Variables which get value from user, answers or auto expression.
+     */
+    pub fn is_value(&self) -> bool {
+        matches!(
+            self,
+            TemplateVariable::Var
+                | TemplateVariable::Select
+                | TemplateVariable::Bool
+                | TemplateVariable::List
+        )
+    }
+
+    /**
+     * `__each__` / `__endeach__` repeat their content for every item of list variable.
+     */
+    pub fn is_repeat(&self) -> bool {
+        self == &TemplateVariable::Each || self == &TemplateVariable::EndEach
+    }
+
+    /**
+     * Block tokens (`__if__`, `__elif__`, `__else__`, `__endif__`) are not replaced by value,
+     * they decide which parts of the template are rendered.
+     */
+    pub fn is_block(&self) -> bool {
+        matches!(
+            self,
+            TemplateVariable::If
+                | TemplateVariable::Elif
+                | TemplateVariable::Else
+                | TemplateVariable::EndIf
+        )
+    }
+
     pub fn from_str(value: &str) -> Option<TemplateVariable> {
         match value {
             "__var__" => Some(TemplateVariable::Var),
             "__select__" => Some(TemplateVariable::Select),
+            "__bool__" => Some(TemplateVariable::Bool),
+            "__list__" => Some(TemplateVariable::List),
+            "__if__" => Some(TemplateVariable::If),
+            "__elif__" => Some(TemplateVariable::Elif),
+            "__else__" => Some(TemplateVariable::Else),
+            "__endif__" => Some(TemplateVariable::EndIf),
+            "__each__" => Some(TemplateVariable::Each),
+            "__endeach__" => Some(TemplateVariable::EndEach),
+            "__index__" => Some(TemplateVariable::Index),
+            "__first__" => Some(TemplateVariable::First),
+            "__last__" => Some(TemplateVariable::Last),
             _ => None,
         }
     }
 }
 
+/**
+ * Condition of `__if__` / `__elif__` block, for example `__if__select__kind__complex__`
+ * or `__if__bool__stories__`. `__each__` uses it to point to its list variable.
+ */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TemplateCondition {
+    pub template_variable: TemplateVariable,
+    pub var_name: String,
+    /// expected value, without it condition checks if value is set (or true for bool)
+    pub value: Option<String>,
+}
+
+impl TemplateCondition {
+    /**
+     * Variable which is checked by the condition, so it can be resolved as any other variable.
+     */
+    pub fn to_variable_info(&self) -> TemplateVariableInfo {
+        let raw_value = format!("{}{}__", self.template_variable, self.var_name);
+        TemplateVariableInfo {
+            template_variable: self.template_variable,
+            var_name: self.var_name.to_owned(),
+            case_type: CaseType::Unknown,
+            start_index: 0,
+            end_index: raw_value.len(),
+            raw_value,
+            is_auto: false,
+            condition: None,
+        }
+    }
+}
+
+/// case suffixes added after `kebab`, `snake`, `camel` and `pascal`, templates may use them as names
+const NAME_LIKE_CASE_SUFFIXES: [&str; 8] = [
+    "screaming",
+    "title",
+    "dot",
+    "path",
+    "flat",
+    "train",
+    "lower",
+    "upper",
+];
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TemplateVariableInfo {
     pub template_variable: TemplateVariable,
@@ -42,6 +160,8 @@ pub struct TemplateVariableInfo {
     pub end_index: usize,
     pub raw_value: String,
     pub is_auto: bool,
+    /// only for `__if__`, `__elif__` and `__each__`, None when condition is invalid
+    pub condition: Option<TemplateCondition>,
 }
 
 impl TemplateVariableInfo {
@@ -69,7 +189,14 @@ impl TemplateVariableInfo {
             end_index: full.end(),
             raw_value: full.as_str().to_string(),
             is_auto: false,
+            condition: None,
         };
+        if template_variable.is_block() || template_variable.is_repeat() {
+            return Some(TemplateVariableInfo::parse_block(
+                template_variable_info,
+                [second_argument, third_argument, fourth_argument],
+            ));
+        }
         if second_argument.is_none() {
             return Some(template_variable_info);
         }
@@ -87,7 +214,11 @@ impl TemplateVariableInfo {
             return Some(template_variable_info);
         }
 
-        if TemplateVariableInfo::is_case_type(second_argument_str) {
+        // original case suffixes right after variable are always case, newer ones were valid
+        // names before, so they are names when anything follows, like `__var__title__kebab__`
+        let is_name_before_case = third_argument.is_some()
+            && NAME_LIKE_CASE_SUFFIXES.contains(&second_argument_str.trim_end_matches("__"));
+        if !is_name_before_case && TemplateVariableInfo::is_case_type(second_argument_str) {
             template_variable_info.case_type =
                 TemplateVariableInfo::get_case_type(second_argument_str);
             return Some(template_variable_info);
@@ -123,23 +254,114 @@ impl TemplateVariableInfo {
         Some(template_variable_info)
     }
 
-    fn is_case_type(value: &str) -> bool {
-        TemplateVariableInfo::get_case_type(value) != CaseType::Unknown
+    /**
+     * Name of the variable when token like `__var__title__` is read as case, but it was
+     * a variable named `title` before the case existed.
+     */
+    pub fn name_like_case(&self) -> Option<&str> {
+        if !self.var_name.is_empty() || self.is_auto || !self.template_variable.is_value() {
+            return None;
+        }
+        let prefix = self.template_variable.to_string();
+        let name = self.raw_value.strip_prefix(&prefix)?.strip_suffix("__")?;
+        NAME_LIKE_CASE_SUFFIXES.contains(&name).then_some(name)
     }
-    fn get_case_type(value: &str) -> CaseType {
-        if value.starts_with("kebab__") {
-            return CaseType::KebabCase;
+
+    /**
+     * Token read as variable named by its case suffix, see `name_like_case`.
+     */
+    pub fn as_named(&self) -> TemplateVariableInfo {
+        match self.name_like_case() {
+            Some(name) => TemplateVariableInfo {
+                var_name: name.to_string(),
+                case_type: CaseType::Unknown,
+                ..self.clone()
+            },
+            None => self.clone(),
         }
-        if value.starts_with("snake__") {
-            return CaseType::SnakeCase;
+    }
+
+    /**
+     * `__else__`, `__endif__` and `__endeach__` have no arguments, so anything after them is left as text.
+     * `__if__` and `__elif__` are followed by variable type, name and optional expected value,
+     * or by `first__` / `last__` helper. `__each__` is followed by name of the list.
+     */
+    fn parse_block(
+        mut template_variable_info: TemplateVariableInfo,
+        arguments: [Option<regex::Match>; 3],
+    ) -> TemplateVariableInfo {
+        let template_variable = template_variable_info.template_variable;
+        if template_variable == TemplateVariable::Else
+            || template_variable == TemplateVariable::EndIf
+            || template_variable == TemplateVariable::EndEach
+        {
+            let length = template_variable.to_string().len();
+            TemplateVariableInfo::truncate(&mut template_variable_info, length);
+            return template_variable_info;
+        }
+
+        let [variable, name, value] =
+            arguments.map(|argument| argument.map(|item| item.as_str().trim_end_matches("__")));
+        if template_variable == TemplateVariable::Each {
+            // name is optional, `__each__` repeats unnamed `__list__`
+            let length = match variable {
+                Some(_) => arguments[0].unwrap().end() - template_variable_info.start_index,
+                None => template_variable.to_string().len(),
+            };
+            TemplateVariableInfo::truncate(&mut template_variable_info, length);
+            template_variable_info.condition = Some(TemplateCondition {
+                template_variable: TemplateVariable::List,
+                var_name: variable.unwrap_or_default().to_string(),
+                value: None,
+            });
+            return template_variable_info;
         }
-        if value.starts_with("camel__") {
-            return CaseType::CamelCase;
+
+        let variable =
+            variable.and_then(|variable| TemplateVariable::from_str(&format!("__{}__", variable)));
+        if let Some(helper @ (TemplateVariable::First | TemplateVariable::Last)) = variable {
+            let length = arguments[0].unwrap().end() - template_variable_info.start_index;
+            TemplateVariableInfo::truncate(&mut template_variable_info, length);
+            template_variable_info.condition = Some(TemplateCondition {
+                template_variable: helper,
+                var_name: "".to_string(),
+                value: None,
+            });
+            return template_variable_info;
         }
-        if value.starts_with("pascal__") {
-            return CaseType::PascalCase;
+        if let (
+            Some(
+                variable @ (TemplateVariable::Var
+                | TemplateVariable::Select
+                | TemplateVariable::Bool
+                | TemplateVariable::List),
+            ),
+            Some(name),
+        ) = (variable, name)
+        {
+            template_variable_info.condition = Some(TemplateCondition {
+                template_variable: variable,
+                var_name: name.to_string(),
+                value: value.map(|value| value.to_string()),
+            });
         }
-        CaseType::Unknown
+        template_variable_info
+    }
+
+    /**
+     * Regex matches as many arguments as possible, tokens without them have to be cut,
+     * so the rest is left as text.
+     */
+    fn truncate(template_variable_info: &mut TemplateVariableInfo, length: usize) {
+        template_variable_info.end_index = template_variable_info.start_index + length;
+        template_variable_info.raw_value = template_variable_info.raw_value[..length].to_string();
+    }
+
+    fn is_case_type(value: &str) -> bool {
+        TemplateVariableInfo::get_case_type(value) != CaseType::Unknown
+    }
+    fn get_case_type(value: &str) -> CaseType {
+        CaseType::from_suffix(value)
     }
 
     fn from_str_at_index(value: &str, start_index: usize) -> Option<TemplateVariableInfo> {
@@ -147,7 +369,7 @@ impl TemplateVariableInfo {
         TemplateVariableInfo::from_str(text)
     }
 
-    pub fn parse_iter(str: &str) -> TemplateVariableInfoIterator {
+    pub fn parse_iter(str: &str) -> TemplateVariableInfoIterator<'_> {
         TemplateVariableInfoIterator {
             content: Some(str),
             last_index: 0,
@@ -181,6 +403,7 @@ impl Iterator for TemplateVariableInfoIterator<'_> {
             template_variable: result.template_variable,
             raw_value: result.raw_value,
             is_auto: result.is_auto,
+            condition: result.condition,
         });
         self.last_index += result.end_index;
         item_result
@@ -299,4 +522,118 @@ mod tests {
         assert_eq!(result.case_type, CaseType::KebabCase);
         assert_eq!(result.raw_value, "__var__kebab__");
     }
+
+    #[test]
+    fn should_parse_extended_case_types() {
+        let result = TemplateVariableInfo::from_str("__var__const__screaming__").unwrap();
+        assert_eq!(result.var_name, "const");
+        assert_eq!(result.case_type, CaseType::ScreamingSnakeCase);
+
+        let result = TemplateVariableInfo::from_str("__select__dot__").unwrap();
+        assert_eq!(result.var_name, "");
+        assert_eq!(result.case_type, CaseType::DotCase);
+
+        let result = TemplateVariableInfo::from_str("__var__title__flat__").unwrap();
+        assert_eq!(result.var_name, "title");
+        assert_eq!(result.case_type, CaseType::FlatCase);
+
+        let result = TemplateVariableInfo::from_str("__var__pkg__path__auto__").unwrap();
+        assert_eq!(result.var_name, "pkg");
+        assert_eq!(result.case_type, CaseType::PathCase);
+        assert!(result.is_auto);
+    }
+
+    #[test]
+    fn should_keep_old_parse_of_name_like_case_suffixes() {
+        // original case suffix in second place is case, the rest is ignored as before
+        let result = TemplateVariableInfo::from_str("__var__kebab__snake__").unwrap();
+        assert_eq!(result.var_name, "");
+        assert_eq!(result.case_type, CaseType::KebabCase);
+        assert_eq!(result.raw_value, "__var__kebab__snake__");
+
+        // newer case suffix followed by anything is a name
+        let result = TemplateVariableInfo::from_str("__var__title__auto__").unwrap();
+        assert_eq!(result.var_name, "title");
+        assert!(result.is_auto);
+        let result = TemplateVariableInfo::from_str("__var__upper__kebab__").unwrap();
+        assert_eq!(result.var_name, "upper");
+        assert_eq!(result.case_type, CaseType::KebabCase);
+
+        // alone it's case, or variable `title` when template has it
+        let result = TemplateVariableInfo::from_str("__var__title__").unwrap();
+        assert_eq!(result.var_name, "");
+        assert_eq!(result.case_type, CaseType::TitleCase);
+        assert_eq!(result.name_like_case(), Some("title"));
+        let named = result.as_named();
+        assert_eq!(named.var_name, "title");
+        assert_eq!(named.case_type, CaseType::Unknown);
+        let result = TemplateVariableInfo::from_str("__var__kebab__").unwrap();
+        assert_eq!(result.name_like_case(), None);
+    }
+
+    #[test]
+    fn should_parse_block_tokens() {
+        let search = "a __if__select__kind__complex__ b __elif__bool__stories__ __else____endif__";
+        let mut iterator = TemplateVariableInfo::parse_iter(search);
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::If);
+        assert_eq!(result.raw_value, "__if__select__kind__complex__");
+        let condition = result.condition.unwrap();
+        assert_eq!(condition.template_variable, TemplateVariable::Select);
+        assert_eq!(condition.var_name, "kind");
+        assert_eq!(condition.value.unwrap(), "complex");
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::Elif);
+        let condition = result.condition.unwrap();
+        assert_eq!(condition.template_variable, TemplateVariable::Bool);
+        assert_eq!(condition.value, None);
+        assert_eq!(condition.to_variable_info().raw_value, "__bool__stories__");
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::Else);
+        assert_eq!(&search[result.start_index..result.end_index], "__else__");
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::EndIf);
+        assert!(iterator.next().is_none());
+
+        let result = TemplateVariableInfo::from_str("__if__name__").unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::If);
+        assert!(result.condition.is_none());
+    }
+
+    #[test]
+    fn should_parse_repeat_tokens() {
+        let search = "x __each__fields__ __if__last____else__, __endif__ __index__ __endeach__";
+        let mut iterator = TemplateVariableInfo::parse_iter(search);
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::Each);
+        assert_eq!(
+            &search[result.start_index..result.end_index],
+            "__each__fields__"
+        );
+        let condition = result.condition.unwrap();
+        assert_eq!(condition.template_variable, TemplateVariable::List);
+        assert_eq!(condition.var_name, "fields");
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.raw_value, "__if__last__");
+        assert_eq!(
+            result.condition.unwrap().template_variable,
+            TemplateVariable::Last
+        );
+
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::Else);
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::EndIf);
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::Index);
+        let result = iterator.next().unwrap();
+        assert_eq!(result.template_variable, TemplateVariable::EndEach);
+        assert!(iterator.next().is_none());
+    }
 }